@@ -1,359 +1,28 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use lofty::{flac::FlacFile, Accessor, AudioFile, Probe, TaggedFileExt};
-use metaflac::{block::VorbisComment, Tag};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use lofty::{ParseOptions, ParsingMode};
+use std::io::Cursor;
 use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
 use symphonia::core::probe::Hint;
-
-pub struct AudioMetadata {
-    pub name: String,
-    pub album: String,
-    pub album_artist: String,
-    pub year: u32,
-    pub track: u32,
-    pub path: String,
-    pub lossless: bool,
-    pub duration: u64,
-}
-impl Default for AudioMetadata {
-    fn default() -> AudioMetadata {
-        AudioMetadata {
-            name: String::from(""),
-            album: String::from(""),
-            album_artist: String::from(""),
-            year: 0,
-            track: 0,
-            path: String::from(""),
-            lossless: false,
-            duration: 0,
-        }
-    }
-}
-pub fn get_metadata_metaflac_reader(path: String) -> AudioMetadata {
-    let file = std::fs::File::open(&path).unwrap();
-    let mut reader = std::io::BufReader::new(file);
-    let tag = Tag::read_from(&mut reader).unwrap();
-    let vorbis: &VorbisComment = tag.vorbis_comments().unwrap();
-
-    let mut stream_info = tag.get_blocks(metaflac::BlockType::StreamInfo);
-    let duration = match stream_info.next() {
-        Some(metaflac::Block::StreamInfo(s)) => Some(s.total_samples as u64 / s.sample_rate as u64),
-        _ => None,
-    };
-    let metadata: AudioMetadata = AudioMetadata {
-        name: vorbis
-            .title()
-            .map(|v| v[0].clone())
-            .unwrap_or_else(|| "FAILED TO READ TITLE DEAFTONE".to_string()),
-        album: vorbis
-            .album()
-            .map(|v| v[0].clone())
-            .unwrap_or_else(|| "FAILED TO READ ALBUM DEAFTONE".to_string()),
-        album_artist: match vorbis.album_artist().map(|v| v[0].clone()) {
-            Some(e) => e,
-            None => vorbis
-                .artist()
-                .map(|v| v[0].clone())
-                .unwrap_or_else(|| "FAILED TO READ ARTIST DEAFTONE".to_string()),
-        },
-        year: get_year(vorbis),
-        track: vorbis.track().unwrap_or(0),
-        path,
-        lossless: true,
-        duration: duration.unwrap_or_default(),
-    };
-    metadata
-}
-
-pub fn get_metadata_metaflac(path: String) -> AudioMetadata {
-    let tag = Tag::read_from_path(&path).unwrap();
-    let vorbis: &VorbisComment = tag.vorbis_comments().unwrap();
-
-    let mut stream_info = tag.get_blocks(metaflac::BlockType::StreamInfo);
-    let duration = match stream_info.next() {
-        Some(metaflac::Block::StreamInfo(s)) => Some(s.total_samples as u64 / s.sample_rate as u64),
-        _ => None,
-    };
-    let metadata: AudioMetadata = AudioMetadata {
-        name: vorbis
-            .title()
-            .map(|v| v[0].clone())
-            .unwrap_or_else(|| "FAILED TO READ TITLE DEAFTONE".to_string()),
-        album: vorbis
-            .album()
-            .map(|v| v[0].clone())
-            .unwrap_or_else(|| "FAILED TO READ ALBUM DEAFTONE".to_string()),
-        album_artist: match vorbis.album_artist().map(|v| v[0].clone()) {
-            Some(e) => e,
-            None => vorbis
-                .artist()
-                .map(|v| v[0].clone())
-                .unwrap_or_else(|| "FAILED TO READ ARTIST DEAFTONE".to_string()),
-        },
-        year: get_year(vorbis),
-        track: vorbis.track().unwrap_or(0),
-        path,
-        lossless: true,
-        duration: duration.unwrap_or_default(),
-    };
-    metadata
-}
-
-pub fn get_metadata_symphonia(path: String) -> AudioMetadata {
-    let src = std::fs::File::open(&path).expect("failed to open media");
-    let mss = MediaSourceStream::new(Box::new(src), Default::default());
-    let mut hint = Hint::new();
-    hint.with_extension("flac");
-    let meta_opts: MetadataOptions = Default::default();
-    let fmt_opts: FormatOptions = Default::default();
-    let mut probed = symphonia::default::get_probe()
-        .format(&hint, mss, &fmt_opts, &meta_opts)
-        .expect("unsupported format");
-
-    return if let Some(metadata_rev) = probed.format.metadata().current() {
-        get_tags(&metadata_rev)
-    /*         if probed.metadata.get().as_ref().is_some() {
-        println!("tags that are part of the container format are preferentially printed.");
-        println!("not printing additional tags that were found while probing.");
-    } */
-    } else if let Some(metadata_rev) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
-        get_tags(&metadata_rev)
-    } else {
-        AudioMetadata::default()
-    };
-}
-fn get_tags(metadata_rev: &MetadataRevision) -> AudioMetadata {
-    let tags = metadata_rev.tags();
-    let mut metadata: AudioMetadata = AudioMetadata::default();
-    for tag in tags.iter() {
-        if let Some(std_key) = tag.std_key {
-            match std_key {
-                StandardTagKey::Album => metadata.album = tag.value.to_string(),
-                StandardTagKey::AcoustidFingerprint => todo!(),
-                StandardTagKey::AcoustidId => todo!(),
-                StandardTagKey::AlbumArtist => metadata.album_artist = tag.value.to_string(),
-                StandardTagKey::Arranger => todo!(),
-                StandardTagKey::Artist => (),
-                StandardTagKey::Bpm => todo!(),
-                StandardTagKey::Comment => (),
-                StandardTagKey::Compilation => todo!(),
-                StandardTagKey::Composer => (),
-                StandardTagKey::Conductor => todo!(),
-                StandardTagKey::ContentGroup => todo!(),
-                StandardTagKey::Copyright => todo!(),
-                StandardTagKey::Date => (),
-                StandardTagKey::Description => todo!(),
-                StandardTagKey::DiscNumber => (),
-                StandardTagKey::DiscSubtitle => todo!(),
-                StandardTagKey::DiscTotal => todo!(),
-                StandardTagKey::EncodedBy => todo!(),
-                StandardTagKey::Encoder => todo!(),
-                StandardTagKey::EncoderSettings => todo!(),
-                StandardTagKey::EncodingDate => todo!(),
-                StandardTagKey::Engineer => todo!(),
-                StandardTagKey::Ensemble => todo!(),
-                StandardTagKey::Genre => (),
-                StandardTagKey::IdentAsin => todo!(),
-                StandardTagKey::IdentBarcode => todo!(),
-                StandardTagKey::IdentCatalogNumber => todo!(),
-                StandardTagKey::IdentEanUpn => todo!(),
-                StandardTagKey::IdentIsrc => todo!(),
-                StandardTagKey::IdentPn => todo!(),
-                StandardTagKey::IdentPodcast => todo!(),
-                StandardTagKey::IdentUpc => todo!(),
-                StandardTagKey::Label => todo!(),
-                StandardTagKey::Language => todo!(),
-                StandardTagKey::License => todo!(),
-                StandardTagKey::Lyricist => todo!(),
-                StandardTagKey::Lyrics => todo!(),
-                StandardTagKey::MediaFormat => todo!(),
-                StandardTagKey::MixDj => todo!(),
-                StandardTagKey::MixEngineer => todo!(),
-                StandardTagKey::Mood => todo!(),
-                StandardTagKey::MovementName => todo!(),
-                StandardTagKey::MovementNumber => todo!(),
-                StandardTagKey::MusicBrainzAlbumArtistId => todo!(),
-                StandardTagKey::MusicBrainzAlbumId => todo!(),
-                StandardTagKey::MusicBrainzArtistId => todo!(),
-                StandardTagKey::MusicBrainzDiscId => todo!(),
-                StandardTagKey::MusicBrainzGenreId => todo!(),
-                StandardTagKey::MusicBrainzLabelId => todo!(),
-                StandardTagKey::MusicBrainzOriginalAlbumId => todo!(),
-                StandardTagKey::MusicBrainzOriginalArtistId => todo!(),
-                StandardTagKey::MusicBrainzRecordingId => todo!(),
-                StandardTagKey::MusicBrainzReleaseGroupId => todo!(),
-                StandardTagKey::MusicBrainzReleaseStatus => todo!(),
-                StandardTagKey::MusicBrainzReleaseTrackId => todo!(),
-                StandardTagKey::MusicBrainzReleaseType => todo!(),
-                StandardTagKey::MusicBrainzTrackId => todo!(),
-                StandardTagKey::MusicBrainzWorkId => todo!(),
-                StandardTagKey::Opus => todo!(),
-                StandardTagKey::OriginalAlbum => todo!(),
-                StandardTagKey::OriginalArtist => todo!(),
-                StandardTagKey::OriginalDate => todo!(),
-                StandardTagKey::OriginalFile => todo!(),
-                StandardTagKey::OriginalWriter => todo!(),
-                StandardTagKey::Owner => todo!(),
-                StandardTagKey::Part => todo!(),
-                StandardTagKey::PartTotal => todo!(),
-                StandardTagKey::Performer => todo!(),
-                StandardTagKey::Podcast => todo!(),
-                StandardTagKey::PodcastCategory => todo!(),
-                StandardTagKey::PodcastDescription => todo!(),
-                StandardTagKey::PodcastKeywords => todo!(),
-                StandardTagKey::Producer => todo!(),
-                StandardTagKey::PurchaseDate => todo!(),
-                StandardTagKey::Rating => todo!(),
-                StandardTagKey::ReleaseCountry => todo!(),
-                StandardTagKey::ReleaseDate => todo!(),
-                StandardTagKey::Remixer => todo!(),
-                StandardTagKey::ReplayGainAlbumGain => todo!(),
-                StandardTagKey::ReplayGainAlbumPeak => todo!(),
-                StandardTagKey::ReplayGainTrackGain => todo!(),
-                StandardTagKey::ReplayGainTrackPeak => todo!(),
-                StandardTagKey::Script => todo!(),
-                StandardTagKey::SortAlbum => todo!(),
-                StandardTagKey::SortAlbumArtist => todo!(),
-                StandardTagKey::SortArtist => todo!(),
-                StandardTagKey::SortComposer => todo!(),
-                StandardTagKey::SortTrackTitle => todo!(),
-                StandardTagKey::TaggingDate => todo!(),
-                StandardTagKey::TrackNumber => (),
-                StandardTagKey::TrackSubtitle => todo!(),
-                StandardTagKey::TrackTitle => metadata.name = tag.value.to_string(),
-                StandardTagKey::TrackTotal => todo!(),
-                StandardTagKey::TvEpisode => todo!(),
-                StandardTagKey::TvEpisodeTitle => todo!(),
-                StandardTagKey::TvNetwork => todo!(),
-                StandardTagKey::TvSeason => todo!(),
-                StandardTagKey::TvShowTitle => todo!(),
-                StandardTagKey::Url => todo!(),
-                StandardTagKey::UrlArtist => todo!(),
-                StandardTagKey::UrlCopyright => todo!(),
-                StandardTagKey::UrlInternetRadio => todo!(),
-                StandardTagKey::UrlLabel => todo!(),
-                StandardTagKey::UrlOfficial => todo!(),
-                StandardTagKey::UrlPayment => todo!(),
-                StandardTagKey::UrlPodcast => todo!(),
-                StandardTagKey::UrlPurchase => todo!(),
-                StandardTagKey::UrlSource => todo!(),
-                StandardTagKey::Version => todo!(),
-                StandardTagKey::Writer => todo!(),
-            }
-        }
-    }
-    return metadata;
-}
-
-fn get_year(vorbis: &VorbisComment) -> u32 {
-    let original_year: String = vorbis
-        .comments
-        .get("ORIGINALYEAR")
-        .and_then(|d| d[0].parse::<String>().ok())
-        .unwrap_or_default();
-
-    let date: String = vorbis
-        .comments
-        .get("DATE")
-        .and_then(|d| d[0].parse::<String>().ok())
-        .unwrap_or_default();
-    let year: String = vorbis
-        .comments
-        .get("YEAR")
-        .and_then(|d| d[0].parse::<String>().ok())
-        .unwrap_or_default();
-
-    if year.chars().count() >= 4 {
-        return parse_year(year);
-    } else if date.chars().count() >= 4 {
-        return parse_year(date);
-    } else if original_year.chars().count() >= 4 {
-        return parse_year(original_year);
-    } else {
-        return 0;
-    }
-}
-
-fn parse_year(mut year: String) -> u32 {
-    if year.chars().count() == 10 {
-        year.truncate(4);
-        return year.parse::<u32>().unwrap_or_default();
-    } else {
-        return year.parse::<u32>().unwrap_or_default();
-    }
-}
-
-pub fn get_metadata_lofty(path: String) -> AudioMetadata {
-    let tagged_file = Probe::open(&path)
-        .expect("ERROR: Bad path provided!")
-        .read()
-        .expect("ERROR: Failed to read file!");
-    let tag = match tagged_file.primary_tag() {
-        Some(primary_tag) => primary_tag,
-        None => tagged_file.first_tag().expect("ERROR: No tags found!"),
-    };
-
-    let properties = tagged_file.properties();
-
-    let duration = properties.duration();
-
-    let metadata: AudioMetadata = AudioMetadata {
-        name: tag.title().unwrap().to_string(),
-        track: tag.track().unwrap(),
-        album: tag.album().unwrap().to_string(),
-        album_artist: tag
-            .get_string(&lofty::ItemKey::AlbumArtist)
-            .unwrap_or("None")
-            .to_owned(),
-        year: tag.year().unwrap(),
-        path,
-        lossless: true,
-        duration: duration.as_secs(),
-    };
-
-    metadata
-}
-
-pub fn get_metadata_lofty_specify_type(path: String) -> AudioMetadata {
-    let file = std::fs::File::open(&path).unwrap();
-    let mut reader = std::io::BufReader::new(file);
-    let flac = FlacFile::read_from(&mut reader, lofty::ParseOptions::new()).unwrap();
-    let vorbis = flac.vorbis_comments().unwrap();
-    let properties = flac.properties();
-    let duration = properties.duration();
-
-    let metadata: AudioMetadata = AudioMetadata {
-        name: vorbis.title().unwrap().to_string(),
-        track: vorbis.track().unwrap(),
-        album: vorbis.album().unwrap().to_string(),
-        album_artist: vorbis.get(&String::from("ALBUMARTIST")).unwrap().to_owned(),
-        year: vorbis.year().unwrap(),
-        path,
-        lossless: true,
-        duration: duration.as_secs(),
-    };
-
-    metadata
-}
-
-pub fn get_metadata_taglib_rust(path: String) -> AudioMetadata {
-    let file = taglib::File::new(&path).unwrap();
-    let tags = file.tag().unwrap();
-    let metadata: AudioMetadata = AudioMetadata {
-        name: tags.title().unwrap().to_string(),
-        track: tags.track().unwrap(),
-        album: tags.album().unwrap().to_string(),
-        album_artist: tags.artist().unwrap(),
-        year: tags.year().unwrap(),
-        path,
-        lossless: true,
-        duration: file.audioproperties().unwrap().length().into(),
-    };
-
-    metadata
-}
+use tag_bench::backends::{
+    get_metadata_flac_baseline, get_metadata_lofty, get_metadata_lofty_0_21,
+    get_metadata_lofty_mmap, get_metadata_lofty_specify_type, get_metadata_lofty_specify_type_0_21,
+    get_metadata_lofty_specify_type_mmap, get_metadata_lofty_specify_type_with_options,
+    get_metadata_lofty_with_options, get_metadata_metaflac, get_metadata_metaflac_mmap,
+    get_metadata_metaflac_reader, get_metadata_symphonia, get_metadata_symphonia_mmap,
+    get_metadata_symphonia_with_options, get_metadata_taglib_rust, AudioMetadata, Reader,
+    SymphoniaReader, READERS,
+};
+use tag_bench::corpus::corpus;
+use tag_bench::decode::Decoder;
+use tag_bench::flac;
+use tag_bench::memory;
+use tag_bench::verify;
+use tokio::sync::Semaphore;
 
 fn tag_reader(c: &mut Criterion) {
     let mut group = c.benchmark_group("Flac Tag Reader");
@@ -390,8 +59,650 @@ fn tag_reader(c: &mut Criterion) {
             get_metadata_taglib_rust(Path::new("./full_test.flac").to_string_lossy().to_string())
         })
     });
+    group.bench_function(stringify!("baseline"), |b| {
+        b.iter(|| {
+            get_metadata_flac_baseline(Path::new("./full_test.flac").to_string_lossy().to_string())
+        })
+    });
+    group.finish();
+}
+
+fn bench_files() -> Vec<(&'static str, String)> {
+    let mut files = vec![("small", "./full_test.flac".to_string())];
+    // Large hi-res fixtures are too big to commit, point this at a local file.
+    if let Ok(path) = std::env::var("TAG_BENCH_LARGE_FLAC") {
+        files.push(("large", path));
+    }
+    files
+}
+
+// Resets the peak RSS of this process, runs `f` and returns how far VmHWM
+// rose above the RSS at the reset, in kB. VmHWM covers the whole process, so
+// without the baseline every reader would also be charged for what criterion
+// and earlier readers left resident. Only available on Linux, returns None
+// elsewhere.
+fn peak_rss_kb<F: FnMut()>(mut f: F) -> Option<u64> {
+    if !memory::reset_peak() {
+        return None;
+    }
+    let before = memory::status()?;
+    f();
+    memory::status().map(|status| status.peak_kb.saturating_sub(before.rss_kb))
+}
+
+fn mmap_reader(c: &mut Criterion) {
+    let readers: [(&str, Reader); 9] = [
+        ("lofty", get_metadata_lofty),
+        ("lofty mmap", get_metadata_lofty_mmap),
+        ("lofty specify file type", get_metadata_lofty_specify_type),
+        (
+            "lofty specify file type mmap",
+            get_metadata_lofty_specify_type_mmap,
+        ),
+        ("metaflac", get_metadata_metaflac),
+        ("metaflac read_from", get_metadata_metaflac_reader),
+        ("metaflac mmap", get_metadata_metaflac_mmap),
+        ("symphonia", get_metadata_symphonia),
+        ("symphonia mmap", get_metadata_symphonia_mmap),
+    ];
+
+    let mut group = c.benchmark_group("Flac Tag Reader mmap");
+    for (name, reader) in readers {
+        for (size, path) in bench_files() {
+            match peak_rss_kb(|| {
+                reader(path.clone());
+            }) {
+                Some(kb) => println!("{}/{}: peak RSS +{} kB", name, size, kb),
+                None => println!("{}/{}: peak RSS unavailable", name, size),
+            }
+            group.bench_with_input(BenchmarkId::new(name, size), &path, |b, path| {
+                b.iter(|| reader(path.clone()))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn scan_thread_pool(files: &[String], reader: Reader, threads: usize) -> Vec<Duration> {
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut latencies = Vec::new();
+                    while let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let start = Instant::now();
+                        reader(path.clone());
+                        latencies.push(start.elapsed());
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+async fn scan_tokio(files: &[String], reader: Reader, concurrency: usize) -> Vec<Duration> {
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = Vec::with_capacity(files.len());
+    for path in files {
+        let permit = semaphore.clone().acquire_owned().await.unwrap();
+        let path = path.clone();
+        let start = Instant::now();
+        tasks.push(tokio::spawn(async move {
+            tokio::task::spawn_blocking(move || reader(path))
+                .await
+                .unwrap();
+            drop(permit);
+            start.elapsed()
+        }));
+    }
+    let mut latencies = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        latencies.push(task.await.unwrap());
+    }
+    latencies
+}
+
+fn print_latencies(name: &str, scan: &str, wall: Duration, mut latencies: Vec<Duration>) {
+    if latencies.is_empty() {
+        println!("{} {}: no files", name, scan);
+        return;
+    }
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+    println!(
+        "{} {}: {:.0} files/s, p50 {:?}, p99 {:?}, max {:?}",
+        name,
+        scan,
+        latencies.len() as f64 / wall.as_secs_f64(),
+        percentile(0.5),
+        percentile(0.99),
+        latencies[latencies.len() - 1],
+    );
+}
+
+fn scan(c: &mut Criterion) {
+    let readers: [(&str, Reader); 5] = [
+        ("lofty", get_metadata_lofty),
+        ("metaflac", get_metadata_metaflac),
+        ("symphonia", get_metadata_symphonia),
+        ("taglib-rust", get_metadata_taglib_rust),
+        ("baseline", get_metadata_flac_baseline),
+    ];
+    let files = corpus();
+    let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .max_blocking_threads(threads)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("Flac Scan");
+    group.throughput(Throughput::Elements(files.len() as u64));
+    for (name, reader) in readers {
+        let start = Instant::now();
+        let latencies = scan_thread_pool(&files, reader, threads);
+        print_latencies(name, "thread pool", start.elapsed(), latencies);
+        let start = Instant::now();
+        let latencies = runtime.block_on(scan_tokio(&files, reader, threads));
+        print_latencies(name, "tokio", start.elapsed(), latencies);
+
+        group.bench_function(BenchmarkId::new("thread pool", name), |b| {
+            b.iter(|| scan_thread_pool(&files, reader, threads))
+        });
+        group.bench_function(BenchmarkId::new("tokio", name), |b| {
+            b.to_async(&runtime)
+                .iter(|| scan_tokio(&files, reader, threads))
+        });
+    }
+    group.finish();
+}
+
+// Writes a copy of the fixture whose VORBIS_COMMENT block holds the fixture's
+// own comments followed by `extra`, returning the path and the block size.
+fn generate_flac(name: &str, extra: &[(String, String)]) -> (String, u64) {
+    let fixture = std::fs::read("./full_test.flac").unwrap();
+    let mut cursor = Cursor::new(&fixture[..]);
+    let tag = flac::Tag::read_from(&mut cursor).unwrap();
+    let audio = &fixture[cursor.position() as usize..];
+    let vorbis = tag.vorbis_comments().unwrap();
+
+    let comments: Vec<String> = vorbis
+        .comments
+        .iter()
+        .flat_map(|(key, values)| values.iter().map(move |value| format!("{}={}", key, value)))
+        .chain(
+            extra
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value)),
+        )
+        .collect();
+    let mut block = Vec::new();
+    block.extend_from_slice(&(vorbis.vendor_string.len() as u32).to_le_bytes());
+    block.extend_from_slice(vorbis.vendor_string.as_bytes());
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in &comments {
+        block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        block.extend_from_slice(comment.as_bytes());
+    }
+    assert!(block.len() <= 0xff_ffff, "vorbis comment block too large");
+
+    let mut out = Vec::with_capacity(fixture.len() + block.len());
+    out.extend_from_slice(b"fLaC");
+    // STREAMINFO is always the first block, clear its last-block flag.
+    out.push(fixture[4] & 0x7f);
+    out.extend_from_slice(&fixture[5..8 + 34]);
+    out.push(0x80 | 4);
+    out.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&block);
+    out.extend_from_slice(audio);
+
+    let dir = std::env::temp_dir().join("tag_bench_scaling");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.flac", name));
+    std::fs::write(&path, out).unwrap();
+    (path.to_string_lossy().to_string(), block.len() as u64)
+}
+
+fn tag_size_scaling(c: &mut Criterion) {
+    let readers: [(&str, Reader); 7] = [
+        ("lofty", get_metadata_lofty),
+        ("lofty specify file type", get_metadata_lofty_specify_type),
+        ("symphonia", get_metadata_symphonia),
+        ("metaflac", get_metadata_metaflac),
+        ("metaflac read_from", get_metadata_metaflac_reader),
+        ("taglib-rust", get_metadata_taglib_rust),
+        ("baseline", get_metadata_flac_baseline),
+    ];
+
+    // One oversized field, so the block grows without adding fields.
+    let base_size = generate_flac("base", &[]).1;
+    let block_sizes: Vec<(String, u64)> = [1 << 10, 1 << 14, 1 << 17, 1 << 20, 1 << 22, 0xff_ffff]
+        .iter()
+        .map(|target: &u64| {
+            let value_len = target.saturating_sub(base_size + 4 + "SCALING=".len() as u64);
+            let extra = [("SCALING".to_string(), "a".repeat(value_len as usize))];
+            generate_flac(&format!("bytes_{}", target), &extra)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("Tag Block Size");
+    for (name, reader) in readers {
+        for (path, block_size) in &block_sizes {
+            group.throughput(Throughput::Bytes(*block_size));
+            group.bench_with_input(BenchmarkId::new(name, block_size), path, |b, path| {
+                b.iter(|| reader(path.clone()))
+            });
+        }
+    }
+    group.finish();
+
+    // Many small fields, so the cost per field dominates.
+    let field_counts: Vec<(String, u64)> = [16, 256, 4096, 65536]
+        .iter()
+        .map(|count: &usize| {
+            let extra: Vec<(String, String)> = (0..*count)
+                .map(|i| (format!("FIELD{}", i), format!("value {}", i)))
+                .collect();
+            let path = generate_flac(&format!("fields_{}", count), &extra).0;
+            (path, *count as u64)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("Tag Field Count");
+    for (name, reader) in readers {
+        for (path, field_count) in &field_counts {
+            group.throughput(Throughput::Elements(*field_count));
+            group.bench_with_input(BenchmarkId::new(name, field_count), path, |b, path| {
+                b.iter(|| reader(path.clone()))
+            });
+        }
+    }
+    group.finish();
+}
+
+// Every version of a backend is wrapped in the same `fn(String) -> AudioMetadata`
+// reader. Readers come in pairs of the current and the newer version.
+fn lofty_versions(c: &mut Criterion) {
+    let readers: [(&str, Reader); 4] = [
+        ("lofty 0.11", get_metadata_lofty),
+        ("lofty 0.21", get_metadata_lofty_0_21),
+        (
+            "lofty 0.11 specify file type",
+            get_metadata_lofty_specify_type,
+        ),
+        (
+            "lofty 0.21 specify file type",
+            get_metadata_lofty_specify_type_0_21,
+        ),
+    ];
+    let path = Path::new("./full_test.flac").to_string_lossy().to_string();
+
+    for pair in readers.chunks(2) {
+        let (old_name, old_reader) = pair[0];
+        let (new_name, new_reader) = pair[1];
+        let old = old_reader(path.clone());
+        let new = new_reader(path.clone());
+        if old == new {
+            println!("{}: same output as {}", new_name, old_name);
+        } else {
+            println!(
+                "{}: output differs from {}\n  {:?}\n  {:?}",
+                new_name, old_name, old, new
+            );
+        }
+    }
+
+    let mut group = c.benchmark_group("Lofty Versions");
+    for (name, reader) in readers {
+        group.bench_with_input(BenchmarkId::new(name, "small"), &path, |b, path| {
+            b.iter(|| reader(path.clone()))
+        });
+    }
+    group.finish();
+}
+
+// Spawns a fresh process per iteration. "process" is the wall time from spawn
+// to exit, "first call" the time the child reports for its only read, so the
+// one-time costs criterion's warm-up hides show up here.
+fn first_call(c: &mut Criterion) {
+    let exe = env!("CARGO_BIN_EXE_first_call");
+    let path = Path::new("./full_test.flac").to_string_lossy().to_string();
+    let run = |name: &str| -> (Duration, Duration) {
+        let start = Instant::now();
+        let output = Command::new(exe)
+            .args([name, &path])
+            .output()
+            .expect("failed to run first_call");
+        let wall = start.elapsed();
+        assert!(
+            output.status.success(),
+            "first_call failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let nanos: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .expect("invalid first_call output");
+        (wall, Duration::from_nanos(nanos))
+    };
+
+    let mut group = c.benchmark_group("First Call");
+    group.sample_size(20);
+    for (name, _) in READERS {
+        group.bench_function(BenchmarkId::new("process", name), |b| {
+            b.iter_custom(|iters| (0..iters).map(|_| run(name).0).sum())
+        });
+        group.bench_function(BenchmarkId::new("first call", name), |b| {
+            b.iter_custom(|iters| (0..iters).map(|_| run(name).1).sum())
+        });
+    }
+    group.finish();
+}
+
+// `get_metadata_symphonia` against readers whose probe is built once, with
+// every default format or only FLAC registered, and each with the extension,
+// the MIME type or no hint at all.
+fn symphonia_probe(c: &mut Criterion) {
+    let path = Path::new("./full_test.flac").to_string_lossy().to_string();
+    let mut extension = Hint::new();
+    extension.with_extension("flac");
+    let mut mime_type = Hint::new();
+    mime_type.mime_type("audio/flac");
+    let hints = [
+        ("extension", extension),
+        ("mime type", mime_type),
+        ("no hint", Hint::new()),
+    ];
+    let readers = [
+        ("all formats", SymphoniaReader::all_formats()),
+        ("flac only", SymphoniaReader::flac()),
+    ];
+
+    let mut group = c.benchmark_group("Symphonia Probe");
+    group.bench_function("get_probe per call", |b| {
+        b.iter(|| get_metadata_symphonia(path.clone()))
+    });
+    for (reader_name, reader) in &readers {
+        for (hint_name, hint) in &hints {
+            group.bench_function(BenchmarkId::new(*reader_name, hint_name), |b| {
+                b.iter(|| reader.read(path.clone(), hint))
+            });
+        }
+    }
+    group.finish();
+}
+
+type OptionsReader = fn(String, ParseOptions) -> AudioMetadata;
+
+// Every combination of reading properties and parsing mode, through the
+// probe and through `FlacFile` directly. Combinations whose output differs
+// from the default options are reported, skipping properties leaves
+// `duration` at zero.
+fn lofty_parse_options(c: &mut Criterion) {
+    let path = Path::new("./full_test.flac").to_string_lossy().to_string();
+    let readers: [(&str, OptionsReader); 2] = [
+        ("probe", get_metadata_lofty_with_options),
+        ("flac file", get_metadata_lofty_specify_type_with_options),
+    ];
+    let modes = [
+        ("strict", ParsingMode::Strict),
+        ("relaxed", ParsingMode::Relaxed),
+    ];
+
+    let mut group = c.benchmark_group("Lofty Parse Options");
+    for (reader_name, reader) in readers {
+        let default = reader(path.clone(), ParseOptions::new());
+        for read_properties in [true, false] {
+            for (mode_name, mode) in modes {
+                let options = ParseOptions::new()
+                    .read_properties(read_properties)
+                    .parsing_mode(mode);
+                let name = format!(
+                    "{} properties {}",
+                    if read_properties { "with" } else { "without" },
+                    mode_name
+                );
+                let metadata = reader(path.clone(), options);
+                if metadata != default {
+                    println!(
+                        "{} {}: output differs from default options\n  {:?}\n  {:?}",
+                        reader_name, name, default, metadata
+                    );
+                }
+                group.bench_function(BenchmarkId::new(reader_name, &name), |b| {
+                    b.iter(|| reader(path.clone(), options))
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+// Decoding alone against decoding and hashing the audio as the MD5 check in
+// STREAMINFO needs, in bytes of file read per second.
+fn flac_verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("FLAC Verify");
+    group.sample_size(20);
+    for (size, path) in bench_files() {
+        let bytes = std::fs::metadata(&path).expect("missing fixture").len();
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_with_input(BenchmarkId::new("decode", size), &path, |b, path| {
+            b.iter(|| {
+                let mut decoder = Decoder::open(path).unwrap();
+                while decoder.next_buffer().unwrap().is_some() {}
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decode + md5", size), &path, |b, path| {
+            b.iter(|| verify::verify_flac(path).unwrap())
+        });
+    }
+    group.finish();
+}
+
+// The bundled FLAC fixture, a WAV made from it, the two second MP3, Vorbis
+// and AAC tones in `fixtures/` and any files in `TAG_BENCH_DECODE_FIXTURES`,
+// for decoding longer local files as well.
+fn decode_fixtures() -> Vec<(String, String)> {
+    let mut fixtures = vec![
+        ("flac".to_string(), "./full_test.flac".to_string()),
+        ("wav".to_string(), generate_wav("./full_test.flac")),
+        ("mp3".to_string(), "./fixtures/tone.mp3".to_string()),
+        ("vorbis".to_string(), "./fixtures/tone.ogg".to_string()),
+        ("aac".to_string(), "./fixtures/tone.m4a".to_string()),
+    ];
+    if let Ok(dir) = std::env::var("TAG_BENCH_DECODE_FIXTURES") {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .expect("failed to read TAG_BENCH_DECODE_FIXTURES")
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            fixtures.push((name, path.to_string_lossy().to_string()));
+        }
+    }
+    fixtures
+}
+
+// RIFF INFO ids lofty maps to the fields the backends read, and the tags
+// they are copied from.
+const WAV_INFO: [(&[u8; 4], &str); 5] = [
+    (b"INAM", "TITLE"),
+    (b"IART", "ARTIST"),
+    (b"IPRD", "ALBUM"),
+    (b"IPRT", "TRACKNUMBER"),
+    (b"ICRD", "DATE"),
+];
+
+// Decodes `source` into a 16 bit PCM WAV file, with its tags in a LIST INFO
+// chunk, and returns its path.
+fn generate_wav(source: &str) -> String {
+    let mut decoder = Decoder::open(source).unwrap();
+    let mut info = b"INFO".to_vec();
+    for (id, key) in WAV_INFO {
+        if let Some(value) = decoder.tags.get(key).and_then(|values| values.first()) {
+            let mut value = value.clone().into_bytes();
+            value.push(0);
+            info.extend_from_slice(id);
+            info.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            info.extend_from_slice(&value);
+            if value.len() % 2 == 1 {
+                info.push(0);
+            }
+        }
+    }
+    let channels = decoder.channels() as u16;
+    let sample_rate = decoder.sample_rate();
+    let mut data = Vec::new();
+    decoder
+        .for_each_interleaved(|samples| {
+            for sample in samples {
+                let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                data.extend_from_slice(&sample.to_le_bytes());
+            }
+        })
+        .unwrap();
+
+    let mut out = Vec::with_capacity(52 + data.len() + info.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(44 + data.len() as u32 + info.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    out.extend_from_slice(&(channels * 2).to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    out.extend_from_slice(b"LIST");
+    out.extend_from_slice(&(info.len() as u32).to_le_bytes());
+    out.extend_from_slice(&info);
+
+    let dir = std::env::temp_dir().join("tag_bench_decode");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("full_test.wav");
+    std::fs::write(&path, out).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+// Decodes to the end and returns the number of samples over all channels.
+fn decode_all(mut decoder: Decoder) -> u64 {
+    let mut samples = 0;
+    while let Some(decoded) = decoder.next_buffer().unwrap() {
+        samples += (decoded.frames() * decoded.spec().channels.count()) as u64;
+    }
+    samples
+}
+
+// Samples per second for every codec, decoding alone and decoding plus a
+// lofty read of the tags, as a player that shows them would do. Symphonia
+// parses the tags while probing either way, it has no option to skip them.
+// The realtime factor of a single decode is printed up front, criterion only
+// reports the throughput.
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Decode");
+    group.sample_size(20);
+    for (name, path) in decode_fixtures() {
+        let decoder = Decoder::open_audio_only(&path).unwrap();
+        let samples_per_second = decoder.sample_rate() as f64 * decoder.channels() as f64;
+        let start = Instant::now();
+        let samples = decode_all(decoder);
+        let elapsed = start.elapsed();
+        let duration = samples as f64 / samples_per_second;
+        println!(
+            "{}: {} samples, {:.1}x realtime",
+            name,
+            samples,
+            duration / elapsed.as_secs_f64()
+        );
+
+        group.throughput(Throughput::Elements(samples));
+        group.bench_with_input(BenchmarkId::new("decode", &name), &path, |b, path| {
+            b.iter(|| decode_all(Decoder::open_audio_only(path).unwrap()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decode + lofty", &name),
+            &path,
+            |b, path| {
+                b.iter(|| {
+                    (
+                        decode_all(Decoder::open_audio_only(path).unwrap()),
+                        get_metadata_lofty(path.clone()),
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// `get_metadata_symphonia` with and without `enable_gapless`, on the decode
+// fixtures and the Opus tone, which symphonia probes but cannot decode. The
+// MP3 carries a LAME header, the AAC an iTunSMPB tag, the Opus its pre-skip
+// and both Ogg files an end trim in the last granule position. The gapless
+// values and any change in duration are printed up front.
+fn symphonia_gapless(c: &mut Criterion) {
+    let options = [
+        ("gapless off", FormatOptions::default()),
+        (
+            "gapless on",
+            FormatOptions {
+                enable_gapless: true,
+                ..Default::default()
+            },
+        ),
+    ];
+
+    let mut fixtures = decode_fixtures();
+    fixtures.push(("opus".to_string(), "./fixtures/tone.opus".to_string()));
+
+    let mut group = c.benchmark_group("Symphonia Gapless");
+    for (name, path) in fixtures {
+        let off = get_metadata_symphonia_with_options(path.clone(), options[0].1);
+        let on = get_metadata_symphonia_with_options(path.clone(), options[1].1);
+        let seconds = |metadata: &AudioMetadata| {
+            metadata
+                .precise_duration
+                .map_or(f64::NAN, |duration| duration.as_secs_f64())
+        };
+        println!(
+            "{}: {:?}, duration {:.3} s, {:.3} s with gapless",
+            name,
+            on.gapless,
+            seconds(&off),
+            seconds(&on)
+        );
+
+        for (option_name, fmt_opts) in options {
+            group.bench_with_input(BenchmarkId::new(option_name, &name), &path, |b, path| {
+                b.iter(|| get_metadata_symphonia_with_options(path.clone(), fmt_opts))
+            });
+        }
+    }
     group.finish();
 }
 
-criterion_group!(benches, tag_reader);
+criterion_group!(
+    benches,
+    tag_reader,
+    mmap_reader,
+    scan,
+    tag_size_scaling,
+    lofty_versions,
+    first_call,
+    symphonia_probe,
+    lofty_parse_options,
+    flac_verify,
+    decode,
+    symphonia_gapless
+);
 criterion_main!(benches);
@@ -0,0 +1,988 @@
+use lofty::{flac::FlacFile, Accessor, AudioFile, Probe, TaggedFile, TaggedFileExt};
+use lofty_0_21::prelude::{Accessor as _, AudioFile as _, TaggedFileExt as _};
+use memmap2::Mmap;
+use metaflac::{block::VorbisComment, Tag};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::time::Duration;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{
+    Metadata, MetadataBuilder, MetadataOptions, MetadataRevision, StandardTagKey,
+};
+use symphonia::core::probe::{Hint, Probe as FormatProbe};
+use symphonia::default::formats::FlacReader;
+use symphonia_metadata::id3v2::Id3v2Reader;
+
+use crate::flac;
+use crate::gapless::{self, Gapless};
+use crate::lossless::LosslessCheck;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct AudioMetadata {
+    pub name: String,
+    pub album: String,
+    // The first artist, as the backend's own accessor reads it. ARTIST, TPE1
+    // or ©ART also stay in `extras` under their raw key.
+    pub artist: String,
+    // Filled in from `tagged_album_artist` by an `AlbumArtistPolicy`.
+    pub album_artist: String,
+    // The album artist as tagged, empty when the file has none.
+    pub tagged_album_artist: String,
+    pub year: u32,
+    pub track: u32,
+    pub path: String,
+    pub lossless: bool,
+    // Whole seconds, truncated.
+    pub duration: u64,
+    // The duration from the sample count and rate in the stream headers, or as
+    // precise as the backend reports it. None when the backend only knows
+    // whole seconds.
+    pub precise_duration: Option<Duration>,
+    // Every tag without a field of its own, keyed by the upper-cased raw key.
+    pub extras: BTreeMap<String, Vec<String>>,
+    // Set by `lossless::analyse`, which decodes the audio.
+    pub lossless_check: Option<LosslessCheck>,
+    // Encoder delay and padding from the stream headers or iTunSMPB, for the
+    // formats that have them. Backends other than symphonia only see
+    // iTunSMPB.
+    pub gapless: Option<Gapless>,
+}
+
+// Vorbis comment names read into one of the named fields, which stay out of
+// `extras`. Backends with their own tag model exclude the same fields by their
+// own keys, see `LOFTY_FIELD_KEYS` and `get_tags`.
+const FIELD_KEYS: [&str; 4] = ["TITLE", "ALBUM", "ALBUMARTIST", "TRACKNUMBER"];
+
+// Vorbis comment field names are case-insensitive, so keys are upper-cased
+// before they are compared or stored.
+fn add_extra(extras: &mut BTreeMap<String, Vec<String>>, key: &str, value: String) {
+    extras
+        .entry(key.to_ascii_uppercase())
+        .or_default()
+        .push(value);
+}
+
+fn add_vorbis_extra(extras: &mut BTreeMap<String, Vec<String>>, key: &str, value: String) {
+    if !FIELD_KEYS.contains(&key.to_ascii_uppercase().as_str()) {
+        add_extra(extras, key, value);
+    }
+}
+
+fn vorbis_extras<'a, I>(comments: I) -> BTreeMap<String, Vec<String>>
+where
+    I: IntoIterator<Item = (&'a String, &'a Vec<String>)>,
+{
+    let mut extras = BTreeMap::new();
+    for (key, values) in comments {
+        for value in values {
+            add_vorbis_extra(&mut extras, key, value.clone());
+        }
+    }
+    extras
+}
+
+// How `album_artist` is filled in from `tagged_album_artist`, so the readers
+// agree on files without an album artist tag. Every backend applies the
+// default policy, `read_with_policy` applies another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlbumArtistPolicy {
+    // Use `artist` when there is no album artist.
+    pub fall_back_to_artist: bool,
+    // Replace spellings like "VA" or "various" with `various_artists`.
+    pub detect_various_artists: bool,
+    // Use `various_artists` for files flagged as part of a compilation that
+    // have no album artist.
+    pub use_compilation: bool,
+    pub various_artists: &'static str,
+}
+
+const VARIOUS_ARTISTS_ALIASES: [&str; 5] = ["various artists", "various", "va", "v.a.", "v/a"];
+
+impl AlbumArtistPolicy {
+    pub const fn new() -> AlbumArtistPolicy {
+        AlbumArtistPolicy {
+            fall_back_to_artist: true,
+            detect_various_artists: true,
+            use_compilation: true,
+            various_artists: "Various Artists",
+        }
+    }
+
+    pub fn apply(&self, metadata: &mut AudioMetadata) {
+        metadata.album_artist = metadata.tagged_album_artist.clone();
+        if self.use_compilation && metadata.album_artist.is_empty() && is_compilation(metadata) {
+            metadata.album_artist = self.various_artists.to_string();
+        }
+        if self.fall_back_to_artist && metadata.album_artist.is_empty() {
+            metadata.album_artist = metadata.artist.clone();
+        }
+        if self.detect_various_artists
+            && VARIOUS_ARTISTS_ALIASES
+                .contains(&metadata.album_artist.trim().to_lowercase().as_str())
+        {
+            metadata.album_artist = self.various_artists.to_string();
+        }
+    }
+}
+
+impl Default for AlbumArtistPolicy {
+    fn default() -> AlbumArtistPolicy {
+        AlbumArtistPolicy::new()
+    }
+}
+
+// Vorbis COMPILATION, ID3v2 TCMP or the MP4 cpil atom.
+fn is_compilation(metadata: &AudioMetadata) -> bool {
+    ["COMPILATION", "TCMP", "CPIL"]
+        .iter()
+        .filter_map(|key| metadata.extras.get(*key))
+        .flatten()
+        .any(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+// Reads `path` with `reader` and fills in the album artist as `policy` says
+// instead of the default policy.
+pub fn read_with_policy(reader: Reader, path: String, policy: &AlbumArtistPolicy) -> AudioMetadata {
+    let mut metadata = reader(path);
+    policy.apply(&mut metadata);
+    metadata
+}
+
+// `frames` at `rate` Hz, None when the rate is unknown.
+fn frames_to_duration(frames: u64, rate: u32) -> Option<Duration> {
+    let rate = rate as u64;
+    (rate > 0).then(|| {
+        Duration::from_secs(frames / rate)
+            + Duration::from_nanos(frames % rate * 1_000_000_000 / rate)
+    })
+}
+
+// The last step of every backend.
+fn apply_default_policy(mut metadata: AudioMetadata) -> AudioMetadata {
+    AlbumArtistPolicy::default().apply(&mut metadata);
+    metadata
+}
+
+pub fn get_metadata_metaflac_reader(path: String) -> AudioMetadata {
+    let file = std::fs::File::open(&path).unwrap();
+    let mut reader = std::io::BufReader::new(file);
+    let tag = Tag::read_from(&mut reader).unwrap();
+    metaflac_tag_to_metadata(&tag, path)
+}
+
+pub fn get_metadata_metaflac(path: String) -> AudioMetadata {
+    let tag = Tag::read_from_path(&path).unwrap();
+    metaflac_tag_to_metadata(&tag, path)
+}
+
+pub fn get_metadata_metaflac_mmap(path: String) -> AudioMetadata {
+    let file = std::fs::File::open(&path).unwrap();
+    let mmap = unsafe { Mmap::map(&file).unwrap() };
+    let tag = Tag::read_from(&mut Cursor::new(&mmap[..])).unwrap();
+    metaflac_tag_to_metadata(&tag, path)
+}
+
+fn metaflac_tag_to_metadata(tag: &Tag, path: String) -> AudioMetadata {
+    let vorbis: &VorbisComment = tag.vorbis_comments().unwrap();
+
+    let mut stream_info = tag.get_blocks(metaflac::BlockType::StreamInfo);
+    let duration = match stream_info.next() {
+        Some(metaflac::Block::StreamInfo(s)) => frames_to_duration(s.total_samples, s.sample_rate),
+        _ => None,
+    };
+    apply_default_policy(AudioMetadata {
+        name: vorbis
+            .title()
+            .map(|v| v[0].clone())
+            .unwrap_or_else(|| "FAILED TO READ TITLE DEAFTONE".to_string()),
+        album: vorbis
+            .album()
+            .map(|v| v[0].clone())
+            .unwrap_or_else(|| "FAILED TO READ ALBUM DEAFTONE".to_string()),
+        artist: vorbis.artist().map(|v| v[0].clone()).unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: vorbis
+            .album_artist()
+            .map(|v| v[0].clone())
+            .unwrap_or_default(),
+        year: get_year(&vorbis.comments),
+        track: vorbis.track().unwrap_or(0),
+        path,
+        lossless: true,
+        duration: duration.map(|d| d.as_secs()).unwrap_or_default(),
+        precise_duration: duration,
+        extras: vorbis_extras(&vorbis.comments),
+        lossless_check: None,
+        gapless: None,
+    })
+}
+
+pub fn get_metadata_flac_baseline(path: String) -> AudioMetadata {
+    let tag = flac::Tag::read_from_path(&path).unwrap();
+    let vorbis = tag.vorbis_comments().unwrap();
+
+    let duration = tag
+        .stream_info
+        .as_ref()
+        .and_then(|s| frames_to_duration(s.total_samples, s.sample_rate));
+    apply_default_policy(AudioMetadata {
+        name: vorbis
+            .title()
+            .map(|v| v[0].clone())
+            .unwrap_or_else(|| "FAILED TO READ TITLE DEAFTONE".to_string()),
+        album: vorbis
+            .album()
+            .map(|v| v[0].clone())
+            .unwrap_or_else(|| "FAILED TO READ ALBUM DEAFTONE".to_string()),
+        artist: vorbis.artist().map(|v| v[0].clone()).unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: vorbis
+            .album_artist()
+            .map(|v| v[0].clone())
+            .unwrap_or_default(),
+        year: get_year(&vorbis.comments),
+        track: vorbis.track().unwrap_or(0),
+        path,
+        lossless: true,
+        duration: duration.map(|d| d.as_secs()).unwrap_or_default(),
+        precise_duration: duration,
+        extras: vorbis_extras(&vorbis.comments),
+        lossless_check: None,
+        gapless: None,
+    })
+}
+
+pub fn get_metadata_symphonia(path: String) -> AudioMetadata {
+    get_metadata_symphonia_with_options(path, Default::default())
+}
+
+// `enable_gapless` trims the delay and padding from the reported duration,
+// the values in `gapless` are read either way.
+pub fn get_metadata_symphonia_with_options(path: String, fmt_opts: FormatOptions) -> AudioMetadata {
+    let src = std::fs::File::open(&path).expect("failed to open media");
+    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("flac");
+    let meta_opts: MetadataOptions = Default::default();
+    symphonia_probe_metadata(
+        symphonia::default::get_probe(),
+        mss,
+        &hint,
+        &fmt_opts,
+        &meta_opts,
+        Precedence::Container,
+    )
+}
+
+pub fn get_metadata_symphonia_mmap(path: String) -> AudioMetadata {
+    let src = std::fs::File::open(&path).expect("failed to open media");
+    let mmap = unsafe { Mmap::map(&src).expect("failed to map media") };
+    let mss = MediaSourceStream::new(Box::new(Cursor::new(mmap)), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("flac");
+    let meta_opts: MetadataOptions = Default::default();
+    let fmt_opts: FormatOptions = Default::default();
+    symphonia_probe_metadata(
+        symphonia::default::get_probe(),
+        mss,
+        &hint,
+        &fmt_opts,
+        &meta_opts,
+        Precedence::Container,
+    )
+}
+
+// A symphonia reader that builds its probe and options once. The probe only
+// knows the formats it is built with instead of everything `get_probe()`
+// registers.
+pub struct SymphoniaReader {
+    probe: FormatProbe,
+    fmt_opts: FormatOptions,
+    meta_opts: MetadataOptions,
+    precedence: Precedence,
+}
+
+impl SymphoniaReader {
+    pub fn all_formats() -> SymphoniaReader {
+        let mut probe = FormatProbe::default();
+        symphonia::default::register_enabled_formats(&mut probe);
+        SymphoniaReader::with_probe(probe)
+    }
+
+    // FLAC plus the ID3v2 prefix some taggers put in front of the stream,
+    // which the probe has to skip to find the fLaC marker.
+    pub fn flac() -> SymphoniaReader {
+        let mut probe = FormatProbe::default();
+        probe.register_all::<FlacReader>();
+        probe.register_all::<Id3v2Reader>();
+        SymphoniaReader::with_probe(probe)
+    }
+
+    pub fn with_probe(probe: FormatProbe) -> SymphoniaReader {
+        SymphoniaReader {
+            probe,
+            fmt_opts: Default::default(),
+            meta_opts: Default::default(),
+            precedence: Precedence::Container,
+        }
+    }
+
+    pub fn precedence(mut self, precedence: Precedence) -> SymphoniaReader {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn read(&self, path: String, hint: &Hint) -> AudioMetadata {
+        let src = std::fs::File::open(&path).expect("failed to open media");
+        let mss = MediaSourceStream::new(Box::new(src), Default::default());
+        symphonia_probe_metadata(
+            &self.probe,
+            mss,
+            hint,
+            &self.fmt_opts,
+            &self.meta_opts,
+            self.precedence,
+        )
+    }
+}
+
+fn symphonia_probe_metadata(
+    probe: &FormatProbe,
+    mss: MediaSourceStream,
+    hint: &Hint,
+    fmt_opts: &FormatOptions,
+    meta_opts: &MetadataOptions,
+    precedence: Precedence,
+) -> AudioMetadata {
+    let mut probed = probe
+        .format(hint, mss, fmt_opts, meta_opts)
+        .expect("unsupported format");
+
+    let container = revisions(probed.format.metadata());
+    let probe = probed.metadata.get().map(revisions).unwrap_or_default();
+    let mut metadata = get_tags(&merge_metadata(&container, &probe, precedence).revision);
+    // The duration in the stream headers, symphonia does not decode to count.
+    // With gapless enabled it excludes the delay and padding.
+    if let Some(params) = probed.format.default_track().map(|t| &t.codec_params) {
+        if let (Some(frames), Some(rate)) = (params.n_frames, params.sample_rate) {
+            metadata.precise_duration = frames_to_duration(frames, rate);
+            metadata.duration = metadata
+                .precise_duration
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+        }
+        metadata.gapless = gapless::from_codec_params(params);
+    }
+    if metadata.gapless.is_none() {
+        metadata.gapless = gapless::from_extras(&metadata.extras);
+    }
+    metadata
+}
+
+// Which source wins when a key is found both in the container's own metadata
+// (the Vorbis comments of a FLAC file) and in metadata found while probing
+// (an ID3v2 tag in front of the stream).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precedence {
+    Container,
+    Probe,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagConflict {
+    pub key: String,
+    pub container: Vec<String>,
+    pub probe: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergedMetadata {
+    pub revision: MetadataRevision,
+    pub conflicts: Vec<TagConflict>,
+}
+
+// Every revision in `metadata`, oldest first.
+pub fn revisions(mut metadata: Metadata<'_>) -> Vec<MetadataRevision> {
+    let mut revisions = Vec::new();
+    while let Some(revision) = metadata.pop() {
+        revisions.push(revision);
+    }
+    if let Some(current) = metadata.current() {
+        revisions.push(current.clone());
+    }
+    revisions
+}
+
+// Combines the tags of all revisions from both sources into one revision.
+// Within a source a later revision replaces the keys it sets, across sources
+// `precedence` decides and differing values are reported as conflicts.
+pub fn merge_metadata(
+    container: &[MetadataRevision],
+    probe: &[MetadataRevision],
+    precedence: Precedence,
+) -> MergedMetadata {
+    let container = tags_by_key(container);
+    let probe = tags_by_key(probe);
+    let (preferred, other) = match precedence {
+        Precedence::Container => (&container, &probe),
+        Precedence::Probe => (&probe, &container),
+    };
+
+    let mut builder = MetadataBuilder::new();
+    let mut conflicts = Vec::new();
+    for (key, tags) in preferred {
+        if let Some(other_tags) = other.get(key) {
+            let values = tag_values(tags);
+            let other_values = tag_values(other_tags);
+            if values != other_values {
+                let (container, probe) = match precedence {
+                    Precedence::Container => (values, other_values),
+                    Precedence::Probe => (other_values, values),
+                };
+                conflicts.push(TagConflict {
+                    key: key.clone(),
+                    container,
+                    probe,
+                });
+            }
+        }
+        for tag in tags {
+            builder.add_tag(tag.clone());
+        }
+    }
+    for (key, tags) in other {
+        if !preferred.contains_key(key) {
+            for tag in tags {
+                builder.add_tag(tag.clone());
+            }
+        }
+    }
+
+    MergedMetadata {
+        revision: builder.metadata(),
+        conflicts,
+    }
+}
+
+// Tags grouped by their standard key, or by the upper-cased raw key when
+// symphonia doesn't map it.
+fn tags_by_key(
+    revisions: &[MetadataRevision],
+) -> BTreeMap<String, Vec<symphonia::core::meta::Tag>> {
+    let mut merged: BTreeMap<String, Vec<symphonia::core::meta::Tag>> = BTreeMap::new();
+    for revision in revisions {
+        let mut current: BTreeMap<String, Vec<symphonia::core::meta::Tag>> = BTreeMap::new();
+        for tag in revision.tags() {
+            let key = match tag.std_key {
+                Some(std_key) => format!("{:?}", std_key),
+                None => tag.key.to_ascii_uppercase(),
+            };
+            current.entry(key).or_default().push(tag.clone());
+        }
+        merged.extend(current);
+    }
+    merged
+}
+
+fn tag_values(tags: &[symphonia::core::meta::Tag]) -> Vec<String> {
+    tags.iter().map(|tag| tag.value.to_string()).collect()
+}
+
+// The raw key of a symphonia tag. MP4 atoms have none, only a standard key,
+// whose name is used instead (ARTIST for ©ART).
+pub fn tag_key(tag: &symphonia::core::meta::Tag) -> String {
+    match tag.std_key {
+        Some(std_key) if tag.key.is_empty() => format!("{:?}", std_key).to_ascii_uppercase(),
+        _ => tag.key.to_ascii_uppercase(),
+    }
+}
+
+// Fields and extras from one revision of symphonia metadata.
+pub fn get_tags(metadata_rev: &MetadataRevision) -> AudioMetadata {
+    let tags = metadata_rev.tags();
+    let mut metadata: AudioMetadata = AudioMetadata::default();
+    for tag in tags.iter() {
+        match tag.std_key {
+            Some(StandardTagKey::Album) => metadata.album = tag.value.to_string(),
+            Some(StandardTagKey::AlbumArtist) => {
+                metadata.tagged_album_artist = tag.value.to_string()
+            }
+            Some(StandardTagKey::Artist) => {
+                if metadata.artist.is_empty() {
+                    metadata.artist = tag.value.to_string();
+                }
+                add_extra(&mut metadata.extras, &tag_key(tag), tag.value.to_string())
+            }
+            Some(StandardTagKey::TrackNumber) => (),
+            Some(StandardTagKey::TrackTitle) => metadata.name = tag.value.to_string(),
+            // Every other tag, whether symphonia maps it to a standard key or
+            // not, is kept under its raw key.
+            _ => add_extra(&mut metadata.extras, &tag_key(tag), tag.value.to_string()),
+        }
+    }
+    apply_default_policy(metadata)
+}
+
+fn get_year(comments: &HashMap<String, Vec<String>>) -> u32 {
+    let original_year: String = comments
+        .get("ORIGINALYEAR")
+        .and_then(|d| d[0].parse::<String>().ok())
+        .unwrap_or_default();
+
+    let date: String = comments
+        .get("DATE")
+        .and_then(|d| d[0].parse::<String>().ok())
+        .unwrap_or_default();
+    let year: String = comments
+        .get("YEAR")
+        .and_then(|d| d[0].parse::<String>().ok())
+        .unwrap_or_default();
+
+    if year.chars().count() >= 4 {
+        parse_year(year)
+    } else if date.chars().count() >= 4 {
+        parse_year(date)
+    } else if original_year.chars().count() >= 4 {
+        parse_year(original_year)
+    } else {
+        0
+    }
+}
+
+fn parse_year(mut year: String) -> u32 {
+    if year.chars().count() == 10 {
+        year.truncate(4);
+        year.parse::<u32>().unwrap_or_default()
+    } else {
+        year.parse::<u32>().unwrap_or_default()
+    }
+}
+
+pub fn get_metadata_lofty(path: String) -> AudioMetadata {
+    get_metadata_lofty_with_options(path, lofty::ParseOptions::new())
+}
+
+pub fn get_metadata_lofty_with_options(
+    path: String,
+    options: lofty::ParseOptions,
+) -> AudioMetadata {
+    let tagged_file = Probe::open(&path)
+        .expect("ERROR: Bad path provided!")
+        .options(options)
+        .read()
+        .expect("ERROR: Failed to read file!");
+    lofty_tagged_file_to_metadata(&tagged_file, path)
+}
+
+pub fn get_metadata_lofty_mmap(path: String) -> AudioMetadata {
+    let file = std::fs::File::open(&path).expect("ERROR: Bad path provided!");
+    let mmap = unsafe { Mmap::map(&file).expect("ERROR: Failed to map file!") };
+    let tagged_file = Probe::new(Cursor::new(&mmap[..]))
+        .guess_file_type()
+        .expect("ERROR: Failed to guess file type!")
+        .read()
+        .expect("ERROR: Failed to read file!");
+    lofty_tagged_file_to_metadata(&tagged_file, path)
+}
+
+// Untagged files are read like an empty tag, with empty fields.
+fn lofty_tagged_file_to_metadata(tagged_file: &TaggedFile, path: String) -> AudioMetadata {
+    let empty = lofty::Tag::new(tagged_file.primary_tag_type());
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+        .unwrap_or(&empty);
+
+    let properties = tagged_file.properties();
+
+    let duration = properties.duration();
+    let extras = lofty_extras(tag);
+
+    apply_default_policy(AudioMetadata {
+        name: tag
+            .title()
+            .map(|title| title.to_string())
+            .unwrap_or_default(),
+        track: tag.track().unwrap_or_default(),
+        album: tag
+            .album()
+            .map(|album| album.to_string())
+            .unwrap_or_default(),
+        artist: tag
+            .artist()
+            .map(|artist| artist.to_string())
+            .unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: tag
+            .get_string(&lofty::ItemKey::AlbumArtist)
+            .unwrap_or_default()
+            .to_owned(),
+        year: tag.year().unwrap_or_default(),
+        path,
+        lossless: true,
+        duration: duration.as_secs(),
+        precise_duration: Some(duration),
+        gapless: gapless::from_extras(&extras),
+        extras,
+        lossless_check: None,
+    })
+}
+
+pub fn get_metadata_lofty_specify_type(path: String) -> AudioMetadata {
+    get_metadata_lofty_specify_type_with_options(path, lofty::ParseOptions::new())
+}
+
+pub fn get_metadata_lofty_specify_type_with_options(
+    path: String,
+    options: lofty::ParseOptions,
+) -> AudioMetadata {
+    let file = std::fs::File::open(&path).unwrap();
+    let mut reader = std::io::BufReader::new(file);
+    let flac = FlacFile::read_from(&mut reader, options).unwrap();
+    lofty_flac_to_metadata(&flac, path)
+}
+
+pub fn get_metadata_lofty_specify_type_mmap(path: String) -> AudioMetadata {
+    let file = std::fs::File::open(&path).unwrap();
+    let mmap = unsafe { Mmap::map(&file).unwrap() };
+    let flac =
+        FlacFile::read_from(&mut Cursor::new(&mmap[..]), lofty::ParseOptions::new()).unwrap();
+    lofty_flac_to_metadata(&flac, path)
+}
+
+fn lofty_flac_to_metadata(flac: &FlacFile, path: String) -> AudioMetadata {
+    let empty = lofty::ogg::VorbisComments::default();
+    let vorbis = flac.vorbis_comments().unwrap_or(&empty);
+    let properties = flac.properties();
+    let duration = properties.duration();
+
+    apply_default_policy(AudioMetadata {
+        name: vorbis
+            .title()
+            .map(|title| title.to_string())
+            .unwrap_or_default(),
+        track: vorbis.track().unwrap_or_default(),
+        album: vorbis
+            .album()
+            .map(|album| album.to_string())
+            .unwrap_or_default(),
+        artist: vorbis
+            .artist()
+            .map(|artist| artist.to_string())
+            .unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: vorbis
+            .get(&String::from("ALBUMARTIST"))
+            .unwrap_or_default()
+            .to_owned(),
+        year: vorbis.year().unwrap_or_default(),
+        path,
+        lossless: true,
+        duration: duration.as_secs(),
+        precise_duration: Some(duration),
+        extras: lofty_vorbis_extras(vorbis.items()),
+        lossless_check: None,
+        gapless: None,
+    })
+}
+
+// The items lofty reads into one of the named fields, whatever the tag format
+// calls them (TIT2, ©nam, TITLE, ...).
+const LOFTY_FIELD_KEYS: [lofty::ItemKey; 4] = [
+    lofty::ItemKey::TrackTitle,
+    lofty::ItemKey::AlbumTitle,
+    lofty::ItemKey::AlbumArtist,
+    lofty::ItemKey::TrackNumber,
+];
+
+// Items are kept under the key they were read with.
+fn lofty_extras(tag: &lofty::Tag) -> BTreeMap<String, Vec<String>> {
+    let mut extras = BTreeMap::new();
+    for item in tag.items() {
+        if LOFTY_FIELD_KEYS.contains(item.key()) {
+            continue;
+        }
+        if let (Some(key), Some(value)) = (
+            item.key().map_key(tag.tag_type(), true),
+            item.value().text(),
+        ) {
+            add_extra(&mut extras, key, value.to_string());
+        }
+    }
+    extras
+}
+
+fn lofty_vorbis_extras<'a, I>(items: I) -> BTreeMap<String, Vec<String>>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut extras = BTreeMap::new();
+    for (key, value) in items {
+        add_vorbis_extra(&mut extras, key, value.to_string());
+    }
+    extras
+}
+
+pub fn get_metadata_lofty_0_21(path: String) -> AudioMetadata {
+    let tagged_file = lofty_0_21::probe::Probe::open(&path)
+        .expect("ERROR: Bad path provided!")
+        .read()
+        .expect("ERROR: Failed to read file!");
+    let empty = lofty_0_21::tag::Tag::new(tagged_file.primary_tag_type());
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+        .unwrap_or(&empty);
+
+    let properties = tagged_file.properties();
+
+    let duration = properties.duration();
+    let extras = lofty_0_21_extras(tag);
+
+    apply_default_policy(AudioMetadata {
+        name: tag
+            .title()
+            .map(|title| title.to_string())
+            .unwrap_or_default(),
+        track: tag.track().unwrap_or_default(),
+        album: tag
+            .album()
+            .map(|album| album.to_string())
+            .unwrap_or_default(),
+        artist: tag
+            .artist()
+            .map(|artist| artist.to_string())
+            .unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: tag
+            .get_string(&lofty_0_21::tag::ItemKey::AlbumArtist)
+            .unwrap_or_default()
+            .to_owned(),
+        year: tag.year().unwrap_or_default(),
+        path,
+        lossless: true,
+        duration: duration.as_secs(),
+        precise_duration: Some(duration),
+        gapless: gapless::from_extras(&extras),
+        extras,
+        lossless_check: None,
+    })
+}
+
+pub fn get_metadata_lofty_specify_type_0_21(path: String) -> AudioMetadata {
+    let file = std::fs::File::open(&path).unwrap();
+    let mut reader = std::io::BufReader::new(file);
+    let flac =
+        lofty_0_21::flac::FlacFile::read_from(&mut reader, lofty_0_21::config::ParseOptions::new())
+            .unwrap();
+    let empty = lofty_0_21::ogg::VorbisComments::default();
+    let vorbis = flac.vorbis_comments().unwrap_or(&empty);
+    let properties = flac.properties();
+    let duration = properties.duration();
+
+    apply_default_policy(AudioMetadata {
+        name: vorbis
+            .title()
+            .map(|title| title.to_string())
+            .unwrap_or_default(),
+        track: vorbis.track().unwrap_or_default(),
+        album: vorbis
+            .album()
+            .map(|album| album.to_string())
+            .unwrap_or_default(),
+        artist: vorbis
+            .artist()
+            .map(|artist| artist.to_string())
+            .unwrap_or_default(),
+        album_artist: String::new(),
+        tagged_album_artist: vorbis.get("ALBUMARTIST").unwrap_or_default().to_owned(),
+        year: vorbis.year().unwrap_or_default(),
+        path,
+        lossless: true,
+        duration: duration.as_secs(),
+        precise_duration: Some(duration),
+        extras: lofty_vorbis_extras(vorbis.items()),
+        lossless_check: None,
+        gapless: None,
+    })
+}
+
+const LOFTY_0_21_FIELD_KEYS: [lofty_0_21::tag::ItemKey; 4] = [
+    lofty_0_21::tag::ItemKey::TrackTitle,
+    lofty_0_21::tag::ItemKey::AlbumTitle,
+    lofty_0_21::tag::ItemKey::AlbumArtist,
+    lofty_0_21::tag::ItemKey::TrackNumber,
+];
+
+fn lofty_0_21_extras(tag: &lofty_0_21::tag::Tag) -> BTreeMap<String, Vec<String>> {
+    let mut extras = BTreeMap::new();
+    for item in tag.items() {
+        if LOFTY_0_21_FIELD_KEYS.contains(item.key()) {
+            continue;
+        }
+        if let (Some(key), Some(value)) = (
+            item.key().map_key(tag.tag_type(), true),
+            item.value().text(),
+        ) {
+            add_extra(&mut extras, key, value.to_string());
+        }
+    }
+    extras
+}
+
+pub fn get_metadata_taglib_rust(path: String) -> AudioMetadata {
+    let file = taglib::File::new(&path).unwrap();
+    let tags = file.tag().unwrap();
+    apply_default_policy(AudioMetadata {
+        name: tags.title().unwrap_or_default(),
+        track: tags.track().unwrap_or_default(),
+        album: tags.album().unwrap_or_default(),
+        artist: tags.artist().unwrap_or_default(),
+        album_artist: String::new(),
+        // taglib-rust has no album artist, the policy falls back to `artist`.
+        tagged_album_artist: String::new(),
+        year: tags.year().unwrap_or_default(),
+        path,
+        lossless: true,
+        duration: file.audioproperties().unwrap().length().into(),
+        precise_duration: None,
+        extras: taglib_extras(&tags),
+        lossless_check: None,
+        gapless: None,
+    })
+}
+
+// taglib-rust only exposes the basic tag interface, so its extras are
+// limited to the fields it has that `AudioMetadata` does not.
+fn taglib_extras(tags: &taglib::Tag) -> BTreeMap<String, Vec<String>> {
+    let mut extras = BTreeMap::new();
+    for (key, value) in [
+        ("ARTIST", tags.artist()),
+        ("COMMENT", tags.comment()),
+        ("GENRE", tags.genre()),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            add_extra(&mut extras, key, value);
+        }
+    }
+    extras
+}
+
+pub type Reader = fn(String) -> AudioMetadata;
+
+// The readers compared in `Flac Tag Reader`, for tools that pick one by name.
+pub const READERS: [(&str, Reader); 7] = [
+    ("lofty", get_metadata_lofty),
+    ("lofty specify file type", get_metadata_lofty_specify_type),
+    ("symphonia", get_metadata_symphonia),
+    ("metaflac", get_metadata_metaflac),
+    ("metaflac read_from", get_metadata_metaflac_reader),
+    ("taglib-rust", get_metadata_taglib_rust),
+    ("baseline", get_metadata_flac_baseline),
+];
+
+pub fn reader(name: &str) -> Option<Reader> {
+    READERS
+        .iter()
+        .find(|(reader_name, _)| *reader_name == name)
+        .map(|(_, reader)| *reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia::core::meta::{Tag as SymphoniaTag, Value};
+
+    fn revision(tags: &[(Option<StandardTagKey>, &str, &str)]) -> MetadataRevision {
+        let mut builder = MetadataBuilder::new();
+        for (std_key, key, value) in tags {
+            builder.add_tag(SymphoniaTag::new(*std_key, key, Value::from(*value)));
+        }
+        builder.metadata()
+    }
+
+    #[test]
+    fn merge_metadata_precedence() {
+        let title = Some(StandardTagKey::TrackTitle);
+        let container = [
+            revision(&[(title, "TITLE", "Old"), (None, "MOOD", "calm")]),
+            revision(&[(title, "TITLE", "Container")]),
+        ];
+        let probe = [revision(&[(title, "TIT2", "Probe"), (None, "TXXX", "x")])];
+
+        for (precedence, expected) in [
+            (Precedence::Container, "Container"),
+            (Precedence::Probe, "Probe"),
+        ] {
+            let merged = merge_metadata(&container, &probe, precedence);
+            let metadata = get_tags(&merged.revision);
+            assert_eq!(metadata.name, expected);
+            // Keys from either source are kept, a later revision only
+            // replaces the keys it sets.
+            assert_eq!(metadata.extras["MOOD"], ["calm"]);
+            assert_eq!(metadata.extras["TXXX"], ["x"]);
+            assert_eq!(
+                merged.conflicts,
+                [TagConflict {
+                    key: "TrackTitle".to_string(),
+                    container: vec!["Container".to_string()],
+                    probe: vec!["Probe".to_string()],
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn readers_agree_without_album_artist() {
+        for (name, reader) in READERS {
+            let metadata = reader("fixtures/no_album_artist.flac".to_string());
+            assert_eq!(metadata.tagged_album_artist, "", "{}", name);
+            assert_eq!(metadata.album_artist, "Foo artist", "{}", name);
+        }
+        let policy = AlbumArtistPolicy {
+            fall_back_to_artist: false,
+            ..AlbumArtistPolicy::new()
+        };
+        let path = "fixtures/no_album_artist.flac".to_string();
+        let metadata = read_with_policy(get_metadata_flac_baseline, path, &policy);
+        assert_eq!(metadata.album_artist, "");
+    }
+
+    #[test]
+    fn lofty_reads_untagged_files() {
+        let readers: [Reader; 4] = [
+            get_metadata_lofty,
+            get_metadata_lofty_specify_type,
+            get_metadata_lofty_0_21,
+            get_metadata_lofty_specify_type_0_21,
+        ];
+        for reader in readers {
+            let metadata = reader("fixtures/untagged.flac".to_string());
+            assert_eq!(metadata.name, "");
+            assert_eq!(metadata.track, 0);
+            assert!(metadata.extras.is_empty());
+            assert!(metadata.precise_duration.is_some());
+        }
+    }
+
+    #[test]
+    fn named_fields_stay_out_of_extras() {
+        for path in ["fixtures/tone.mp3", "fixtures/tone.m4a"] {
+            let mut hint = Hint::new();
+            hint.with_extension(path.rsplit('.').next().unwrap());
+            let symphonia = SymphoniaReader::all_formats().read(path.to_string(), &hint);
+            let lofty = get_metadata_lofty(path.to_string());
+            for metadata in [&symphonia, &lofty] {
+                assert!(!metadata.extras.contains_key(""), "{}", path);
+                assert!(!metadata.extras.values().flatten().any(|v| v == "Fixtures"));
+                assert!(!metadata
+                    .extras
+                    .values()
+                    .flatten()
+                    .any(|v| v == "Gapless Tone"));
+            }
+            assert_eq!(symphonia.extras.len(), lofty.extras.len(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tag_bench::results::{self, Benchmark};
+use tag_bench::stats::{self, Rng};
+
+// Pairwise comparison of the backends within a benchmark group, using the
+// raw samples of the latest criterion run instead of each backend's history.
+//
+// usage: compare [--group NAME] [--criterion-dir DIR] [--confidence LEVEL]
+//                [--resamples N] [--noise PERCENT]
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value for {}", arg);
+        std::process::exit(2);
+    })
+}
+
+pub fn main() {
+    let mut group = "Flac Tag Reader".to_string();
+    let mut criterion_dir = results::default_dir();
+    let mut confidence = 0.95;
+    let mut resamples = 10_000;
+    let mut noise = 2.0;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| {
+            eprintln!("missing value for {}", arg);
+            std::process::exit(2);
+        });
+        match arg.as_str() {
+            "--group" => group = value,
+            "--criterion-dir" => criterion_dir = PathBuf::from(value),
+            "--confidence" => confidence = parse(&arg, &value),
+            "--resamples" => resamples = parse(&arg, &value),
+            "--noise" => noise = parse(&arg, &value),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let benchmarks =
+        results::load_benchmarks(&criterion_dir).expect("failed to read criterion results");
+    let mut cases: BTreeMap<String, Vec<(&Benchmark, Vec<f64>)>> = BTreeMap::new();
+    for benchmark in benchmarks.iter().filter(|b| b.group == group) {
+        let samples = results::load_samples(benchmark).expect("failed to read samples");
+        cases
+            .entry(benchmark.case())
+            .or_default()
+            .push((benchmark, samples));
+    }
+    if cases.is_empty() {
+        eprintln!("no benchmarks found for group {}", group);
+        std::process::exit(1);
+    }
+
+    let alpha = 1.0 - confidence;
+    let mut rng = Rng::new(0x7461_675f_6265_6e63);
+    for (case, backends) in &cases {
+        println!("{}", case);
+        for (i, (a, a_samples)) in backends.iter().enumerate() {
+            for (b, b_samples) in &backends[i + 1..] {
+                // Always phrase the result as the faster one against the slower.
+                let (fast, fast_samples, slow, slow_samples) =
+                    if stats::mean(a_samples) <= stats::mean(b_samples) {
+                        (a, a_samples, b, b_samples)
+                    } else {
+                        (b, b_samples, a, a_samples)
+                    };
+                let ratio = stats::mean(slow_samples) / stats::mean(fast_samples);
+                let (lower, upper) = stats::bootstrap_ratio_ci(
+                    slow_samples,
+                    fast_samples,
+                    resamples,
+                    confidence,
+                    &mut rng,
+                );
+                let (_, p) = stats::mann_whitney_u(slow_samples, fast_samples);
+
+                // A difference is noise when it is not significant or when the
+                // interval reaches into the noise band around 1x.
+                let within_noise = p >= alpha || lower <= 1.0 + noise / 100.0;
+                println!(
+                    "  {} is {:.2}x faster than {} [{:.2}, {:.2}] (p = {:.4}){}",
+                    fast.backend(),
+                    ratio,
+                    slow.backend(),
+                    lower,
+                    upper,
+                    p,
+                    if within_noise { " within noise" } else { "" }
+                );
+            }
+        }
+    }
+}
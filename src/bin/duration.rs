@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use tag_bench::backends::{AudioMetadata, Reader, READERS};
+use tag_bench::corpus::{corpus, fixtures};
+use tag_bench::duration::{self, ErrorSummary};
+
+// Decodes every file to count its samples and compares the exact duration
+// with the one each backend reports and with TLEN-style length tags. Prints
+// the error distribution per backend and per format, and the files that are
+// off by more than the tolerance, 50 ms unless given.
+//
+// usage: duration [--tolerance SECONDS] [FILE...]
+//
+// Without files the corpus from `TAG_BENCH_CORPUS` and the bundled fixtures
+// are audited. Backends are compared by their precise duration, taglib-rust
+// only reports whole seconds and is counted as missing. The FLAC-only
+// backends fail on other formats, which is counted and printed with the
+// panic message.
+
+// The source name used for length tags in the tables.
+const TAGS: &str = "length tag";
+
+// Readers that panic on anything but FLAC. A panic in any other reader is a
+// bug and stops the audit.
+const FLAC_ONLY: [&str; 4] = [
+    "lofty specify file type",
+    "metaflac",
+    "metaflac read_from",
+    "baseline",
+];
+
+// Calls a FLAC-only reader and returns its panic message instead of letting
+// the default hook print it.
+fn read_flac_only(reader: Reader, path: &str) -> Result<AudioMetadata, String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| reader(path.to_string())));
+    panic::set_hook(hook);
+    result.map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panicked".to_string())
+    })
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value for {}", arg);
+        std::process::exit(2);
+    })
+}
+
+fn print_table(title: &str, summaries: &BTreeMap<String, ErrorSummary>, tolerance: f64) {
+    println!();
+    println!(
+        "{:<32} {:>6} {:>8} {:>7} {:>10} {:>10} {:>10} {:>10} {:>6}",
+        title, "files", "missing", "failed", "median s", "p50 |s|", "p95 |s|", "max |s|", "over"
+    );
+    for (name, summary) in summaries {
+        println!(
+            "{:<32} {:>6} {:>8} {:>7} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>6}",
+            name,
+            summary.errors.len(),
+            summary.missing,
+            summary.failed,
+            summary.median_error(),
+            summary.abs_percentile(0.5),
+            summary.abs_percentile(0.95),
+            summary.max_abs(),
+            summary.over(tolerance)
+        );
+    }
+}
+
+pub fn main() {
+    let mut tolerance = 0.05;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tolerance" => {
+                let value = args.next().unwrap_or_else(|| {
+                    eprintln!("missing value for {}", arg);
+                    std::process::exit(2);
+                });
+                tolerance = parse(&arg, &value);
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        files = corpus();
+        files.extend(fixtures());
+    }
+
+    let mut by_backend: BTreeMap<String, ErrorSummary> = BTreeMap::new();
+    let mut by_format: BTreeMap<String, ErrorSummary> = BTreeMap::new();
+    for path in &files {
+        let decoded = match duration::decode(path) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                println!("error    {}: {}", path, err);
+                continue;
+            }
+        };
+        let exact = decoded.seconds();
+        let format = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let mut reported: Vec<(String, Option<Option<f64>>)> = READERS
+            .iter()
+            .map(|(name, reader)| {
+                let metadata = if FLAC_ONLY.contains(name) {
+                    match read_flac_only(*reader, path) {
+                        Ok(metadata) => Some(metadata),
+                        Err(message) => {
+                            println!("failed   {}: {}: {}", path, name, message);
+                            None
+                        }
+                    }
+                } else {
+                    Some(reader(path.clone()))
+                };
+                // Zero is what backends report when they have no duration.
+                let seconds = metadata.map(|m| {
+                    m.precise_duration
+                        .map(|d| d.as_secs_f64())
+                        .filter(|s| *s > 0.0)
+                });
+                (name.to_string(), seconds)
+            })
+            .collect();
+        reported.push((
+            TAGS.to_string(),
+            Some(duration::tagged_seconds(&decoded.tags).map(|(_, seconds)| seconds)),
+        ));
+
+        for (name, seconds) in reported {
+            let format_key = format!("{} {}", format, name);
+            for summary in [
+                by_backend.entry(name.clone()).or_default(),
+                by_format.entry(format_key).or_default(),
+            ] {
+                match seconds {
+                    Some(seconds) => summary.add(seconds, exact),
+                    None => summary.failed += 1,
+                }
+            }
+            if let Some(Some(seconds)) = seconds {
+                if (seconds - exact).abs() > tolerance {
+                    println!(
+                        "off      {}: {} reports {:.3} s, decoded {:.3} s ({} frames at {} Hz)",
+                        path, name, seconds, exact, decoded.frames, decoded.sample_rate
+                    );
+                }
+            }
+        }
+    }
+
+    print_table("backend", &by_backend, tolerance);
+    print_table("format and backend", &by_format, tolerance);
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::PathBuf;
+use tag_bench::results::{self, Summary};
+
+// Exports criterion's estimates as CSV, JSON and Markdown and optionally
+// fails when a benchmark got slower than a saved baseline.
+//
+// usage: export [--criterion-dir DIR] [--out DIR] [--save-baseline FILE]
+//               [--baseline FILE] [--threshold PERCENT]
+
+struct Args {
+    criterion_dir: PathBuf,
+    out_dir: PathBuf,
+    save_baseline: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    threshold: f64,
+}
+
+fn parse_args() -> Args {
+    let mut criterion_dir = results::default_dir();
+    let mut out_dir = None;
+    let mut save_baseline = None;
+    let mut baseline = None;
+    let mut threshold = 10.0;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| {
+                eprintln!("missing value for {}", arg);
+                std::process::exit(2);
+            })
+        };
+        match arg.as_str() {
+            "--criterion-dir" => criterion_dir = PathBuf::from(value()),
+            "--out" => out_dir = Some(PathBuf::from(value())),
+            "--save-baseline" => save_baseline = Some(PathBuf::from(value())),
+            "--baseline" => baseline = Some(PathBuf::from(value())),
+            "--threshold" => {
+                threshold = value().parse().unwrap_or_else(|_| {
+                    eprintln!("--threshold expects a percentage");
+                    std::process::exit(2);
+                })
+            }
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    Args {
+        out_dir: out_dir.unwrap_or_else(|| criterion_dir.join("export")),
+        criterion_dir,
+        save_baseline,
+        baseline,
+        threshold,
+    }
+}
+
+pub fn main() {
+    let args = parse_args();
+    let summaries: Vec<Summary> = results::load_benchmarks(&args.criterion_dir)
+        .expect("failed to read criterion results")
+        .iter()
+        .map(Summary::from)
+        .collect();
+    if summaries.is_empty() {
+        eprintln!("no benchmarks found in {}", args.criterion_dir.display());
+        std::process::exit(1);
+    }
+
+    fs::create_dir_all(&args.out_dir).expect("failed to create output directory");
+    fs::write(args.out_dir.join("summary.csv"), to_csv(&summaries)).expect("failed to write csv");
+    fs::write(
+        args.out_dir.join("summary.json"),
+        serde_json::to_string_pretty(&summaries).unwrap(),
+    )
+    .expect("failed to write json");
+    fs::write(args.out_dir.join("summary.md"), to_markdown(&summaries))
+        .expect("failed to write markdown");
+    println!("wrote summaries to {}", args.out_dir.display());
+
+    if let Some(path) = &args.save_baseline {
+        fs::write(path, serde_json::to_string_pretty(&summaries).unwrap())
+            .expect("failed to save baseline");
+        println!("saved baseline to {}", path.display());
+    }
+
+    if let Some(path) = &args.baseline {
+        let baseline: Vec<Summary> = results::read_json(path).expect("failed to read baseline");
+        if !compare(&baseline, &summaries, args.threshold) {
+            std::process::exit(1);
+        }
+    }
+}
+
+fn micros(nanos: f64) -> f64 {
+    nanos / 1000.0
+}
+
+fn to_csv(summaries: &[Summary]) -> String {
+    let mut csv = String::from(
+        "id,group,backend,value,mean_us,mean_lower_us,mean_upper_us,median_us,median_lower_us,median_upper_us,confidence_level\n",
+    );
+    for s in summaries {
+        csv.push_str(&format!(
+            "{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{}\n",
+            csv_field(&s.id),
+            csv_field(&s.group),
+            csv_field(&s.backend),
+            csv_field(s.value.as_deref().unwrap_or("")),
+            micros(s.mean),
+            micros(s.mean_lower),
+            micros(s.mean_upper),
+            micros(s.median),
+            micros(s.median_lower),
+            micros(s.median_upper),
+            s.confidence_level,
+        ));
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_markdown(summaries: &[Summary]) -> String {
+    let mut md = String::from(
+        "| benchmark | mean (µs) | mean CI (µs) | median (µs) | median CI (µs) |\n|---|---:|---:|---:|---:|\n",
+    );
+    for s in summaries {
+        md.push_str(&format!(
+            "| {} | {:.3} | [{:.3}, {:.3}] | {:.3} | [{:.3}, {:.3}] |\n",
+            s.id.replace('|', "\\|"),
+            micros(s.mean),
+            micros(s.mean_lower),
+            micros(s.mean_upper),
+            micros(s.median),
+            micros(s.median_lower),
+            micros(s.median_upper),
+        ));
+    }
+    md
+}
+
+// Returns false when any benchmark's mean grew by more than `threshold`
+// percent over the baseline.
+fn compare(baseline: &[Summary], current: &[Summary], threshold: f64) -> bool {
+    let mut passed = true;
+    for summary in current {
+        let old = match baseline.iter().find(|b| b.id == summary.id) {
+            Some(old) => old,
+            None => {
+                println!("new       {}", summary.id);
+                continue;
+            }
+        };
+        let change = (summary.mean / old.mean - 1.0) * 100.0;
+        let status = if change > threshold {
+            passed = false;
+            "REGRESSED"
+        } else if change < -threshold {
+            "improved"
+        } else {
+            "ok"
+        };
+        println!(
+            "{:<9} {} {:.3} µs -> {:.3} µs ({:+.1}%)",
+            status,
+            summary.id,
+            micros(old.mean),
+            micros(summary.mean),
+            change
+        );
+    }
+    for old in baseline {
+        if !current.iter().any(|s| s.id == old.id) {
+            println!("missing   {}", old.id);
+        }
+    }
+    if !passed {
+        println!("regressions above {}% found", threshold);
+    }
+    passed
+}
@@ -0,0 +1,138 @@
+use tag_bench::backends::{self, AlbumArtistPolicy, AudioMetadata};
+use tag_bench::corpus::corpus;
+use tag_bench::fingerprint::{self, Fingerprint};
+
+// Fingerprints every file, checks the result against its
+// ACOUSTID_FINGERPRINT tag and lists recordings that fingerprint alike but
+// are tagged differently.
+//
+// usage: fingerprint [--reader NAME] [--threshold SIMILARITY] [--print] [FILE...]
+//
+// Without files the corpus from `TAG_BENCH_CORPUS` is fingerprinted. --print
+// writes each fingerprint in the form stored in tags.
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value for {}", arg);
+        std::process::exit(2);
+    })
+}
+
+// Tags that should agree between two copies of the same recording.
+fn differences(a: &AudioMetadata, b: &AudioMetadata) -> Vec<String> {
+    let mut differences = Vec::new();
+    if a.name != b.name {
+        differences.push(format!("title {:?} / {:?}", a.name, b.name));
+    }
+    if a.artist != b.artist {
+        differences.push(format!("artist {:?} / {:?}", a.artist, b.artist));
+    }
+    if a.album != b.album {
+        differences.push(format!("album {:?} / {:?}", a.album, b.album));
+    }
+    if a.album_artist != b.album_artist {
+        differences.push(format!(
+            "album artist {:?} / {:?}",
+            a.album_artist, b.album_artist
+        ));
+    }
+    differences
+}
+
+pub fn main() {
+    let mut reader_name = "symphonia".to_string();
+    let mut threshold = 0.85;
+    let mut print = false;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--reader" | "--threshold" => {
+                let value = args.next().unwrap_or_else(|| {
+                    eprintln!("missing value for {}", arg);
+                    std::process::exit(2);
+                });
+                if arg == "--reader" {
+                    reader_name = value;
+                } else {
+                    threshold = parse(&arg, &value);
+                }
+            }
+            "--print" => print = true,
+            _ => files.push(arg),
+        }
+    }
+    let reader = backends::reader(&reader_name).unwrap_or_else(|| {
+        eprintln!("unknown reader {}", reader_name);
+        std::process::exit(2);
+    });
+    if files.is_empty() {
+        files = corpus();
+    }
+    let policy = AlbumArtistPolicy::default();
+
+    let mut fingerprinted: Vec<(&String, AudioMetadata, Fingerprint)> = Vec::new();
+    let mut mismatched = 0;
+    for path in &files {
+        let computed = match fingerprint::fingerprint(path) {
+            Ok(computed) => computed,
+            Err(err) => {
+                println!("error     {}: {}", path, err);
+                continue;
+            }
+        };
+        let metadata = backends::read_with_policy(reader, path.clone(), &policy);
+        if print {
+            println!("{}: {}", path, computed.encode());
+        }
+        match fingerprint::stored(&metadata.extras) {
+            Some(stored) if stored.algorithm != computed.algorithm => println!(
+                "skipped   {}: stored fingerprint uses algorithm {}",
+                path, stored.algorithm
+            ),
+            Some(stored) => {
+                let similarity = stored.similarity(&computed);
+                if similarity < threshold {
+                    mismatched += 1;
+                    println!(
+                        "MISMATCH  {}: {:.1}% similar to the stored fingerprint",
+                        path,
+                        similarity * 100.0
+                    );
+                } else {
+                    println!("ok        {}: {:.1}% similar", path, similarity * 100.0);
+                }
+            }
+            None => println!("untagged  {}", path),
+        }
+        fingerprinted.push((path, metadata, computed));
+    }
+
+    let mut duplicates = 0;
+    for (i, (a_path, a, a_print)) in fingerprinted.iter().enumerate() {
+        for (b_path, b, b_print) in &fingerprinted[i + 1..] {
+            let similarity = a_print.similarity(b_print);
+            if similarity < threshold {
+                continue;
+            }
+            let differences = differences(a, b);
+            if differences.is_empty() {
+                continue;
+            }
+            duplicates += 1;
+            println!(
+                "duplicate {} and {} ({:.1}% similar): {}",
+                a_path,
+                b_path,
+                similarity * 100.0,
+                differences.join(", ")
+            );
+        }
+    }
+    println!(
+        "{} of {} files differ from their stored fingerprint, {} duplicates tagged differently",
+        mismatched,
+        fingerprinted.len(),
+        duplicates
+    );
+}
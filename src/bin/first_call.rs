@@ -0,0 +1,28 @@
+use std::time::Instant;
+use tag_bench::backends;
+
+// Reads a single file the way a one-shot CLI tool would and prints how long
+// the first call took in nanoseconds, one-time setup included.
+//
+// usage: first_call BACKEND PATH
+
+pub fn main() {
+    let mut args = std::env::args().skip(1);
+    let (backend, path) = match (args.next(), args.next()) {
+        (Some(backend), Some(path)) => (backend, path),
+        _ => {
+            eprintln!("usage: first_call BACKEND PATH");
+            std::process::exit(2);
+        }
+    };
+    let reader = backends::reader(&backend).unwrap_or_else(|| {
+        eprintln!("unknown backend {}", backend);
+        std::process::exit(2);
+    });
+
+    let start = Instant::now();
+    let metadata = reader(path);
+    let elapsed = start.elapsed();
+    std::hint::black_box(metadata);
+    println!("{}", elapsed.as_nanos());
+}
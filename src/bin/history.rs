@@ -0,0 +1,285 @@
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tag_bench::results::{self, Summary};
+
+// Keeps an append-only log of benchmark runs next to the commit, backend
+// versions and compiler they were measured with, and charts it.
+//
+// usage: history record [--criterion-dir DIR] [--store FILE]
+//        history chart [--store FILE] [--out DIR]
+
+const BACKENDS: [&str; 4] = ["lofty", "metaflac", "symphonia", "taglib"];
+const DEFAULT_STORE: &str = "bench_history.jsonl";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Run {
+    timestamp: u64,
+    commit: String,
+    dirty: bool,
+    rustc: String,
+    dependencies: BTreeMap<String, String>,
+    results: Vec<Summary>,
+}
+
+impl Run {
+    fn label(&self) -> String {
+        let commit: String = self.commit.chars().take(7).collect();
+        if self.dirty {
+            format!("{}+", commit)
+        } else {
+            commit
+        }
+    }
+}
+
+pub fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let mut criterion_dir = results::default_dir();
+    let mut store = PathBuf::from(DEFAULT_STORE);
+    let mut out_dir = None;
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| {
+            eprintln!("missing value for {}", arg);
+            std::process::exit(2);
+        });
+        match arg.as_str() {
+            "--criterion-dir" => criterion_dir = PathBuf::from(value),
+            "--store" => store = PathBuf::from(value),
+            "--out" => out_dir = Some(PathBuf::from(value)),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    match command.as_str() {
+        "record" => record(&criterion_dir, &store),
+        "chart" => {
+            let out_dir = out_dir.unwrap_or_else(|| criterion_dir.join("history"));
+            chart(&store, &out_dir)
+        }
+        _ => {
+            eprintln!(
+                "usage: history record|chart [--criterion-dir DIR] [--store FILE] [--out DIR]"
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+fn record(criterion_dir: &Path, store: &Path) {
+    let results: Vec<Summary> = results::load_benchmarks(criterion_dir)
+        .expect("failed to read criterion results")
+        .iter()
+        .map(Summary::from)
+        .collect();
+    if results.is_empty() {
+        eprintln!("no benchmarks found in {}", criterion_dir.display());
+        std::process::exit(1);
+    }
+
+    let run = Run {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        commit: command_output("git", &["rev-parse", "HEAD"]).unwrap_or_default(),
+        dirty: command_output("git", &["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|status| !status.is_empty()),
+        rustc: command_output("rustc", &["--version"]).unwrap_or_default(),
+        dependencies: locked_versions(Path::new("Cargo.lock")),
+        results,
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(store)
+        .expect("failed to open history store");
+    writeln!(file, "{}", serde_json::to_string(&run).unwrap()).expect("failed to append run");
+    println!(
+        "recorded {} results for {} into {}",
+        run.results.len(),
+        run.label(),
+        store.display()
+    );
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Versions of the benchmarked crates as resolved in Cargo.lock. Git
+// dependencies carry the locked revision as well. A crate locked at several
+// versions, like lofty and its renamed newer release, lists all of them.
+fn locked_versions(lock: &Path) -> BTreeMap<String, String> {
+    let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let lock = match fs::read_to_string(lock) {
+        Ok(lock) => lock,
+        Err(_) => return BTreeMap::new(),
+    };
+    for package in lock.split("[[package]]") {
+        let field = |key: &str| {
+            package.lines().find_map(|line| {
+                let value = line.strip_prefix(key)?.trim_start().strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            })
+        };
+        let (name, version) = match (field("name"), field("version")) {
+            (Some(name), Some(version)) => (name, version),
+            _ => continue,
+        };
+        if !BACKENDS.contains(&name.as_str()) {
+            continue;
+        }
+        let version =
+            match field("source").and_then(|s| s.split_once('#').map(|(_, rev)| rev.to_string())) {
+                Some(rev) => format!("{} ({})", version, &rev[..rev.len().min(7)]),
+                None => version,
+            };
+        versions.entry(name).or_default().push(version);
+    }
+    versions
+        .into_iter()
+        .map(|(name, versions)| (name, versions.join(", ")))
+        .collect()
+}
+
+fn load_runs(store: &Path) -> Vec<Run> {
+    let file = fs::File::open(store).expect("failed to open history store");
+    BufReader::new(file)
+        .lines()
+        .map(|line| line.expect("failed to read history store"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(&line).expect("corrupt history entry"))
+        .collect()
+}
+
+fn chart(store: &Path, out_dir: &Path) {
+    let runs = load_runs(store);
+    if runs.is_empty() {
+        eprintln!("no runs recorded in {}", store.display());
+        std::process::exit(1);
+    }
+    fs::create_dir_all(out_dir).expect("failed to create output directory");
+
+    let cases: BTreeSet<String> = runs
+        .iter()
+        .flat_map(|run| run.results.iter().map(|result| result.case()))
+        .collect();
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Tag reader history</title>\n</head>\n<body>\n<h1>Tag reader history</h1>\n",
+    );
+    for (i, case) in cases.iter().enumerate() {
+        let name = format!("trend_{}.svg", i);
+        draw_trend(&out_dir.join(&name), case, &runs).expect("failed to draw trend chart");
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<img src=\"{}\" alt=\"{}\">\n",
+            case, name, case
+        ));
+    }
+
+    html.push_str("<h2>Runs</h2>\n<table border=\"1\">\n<tr><th>#</th><th>commit</th><th>rustc</th><th>dependencies</th></tr>\n");
+    for (i, run) in runs.iter().enumerate() {
+        let dependencies: Vec<String> = run
+            .dependencies
+            .iter()
+            .map(|(name, version)| format!("{} {}", name, version))
+            .collect();
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            i,
+            run.label(),
+            run.rustc,
+            dependencies.join(", ")
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    fs::write(out_dir.join("index.html"), html).expect("failed to write history page");
+    println!("wrote {}", out_dir.join("index.html").display());
+}
+
+// Mean time per run for every backend measured in `case`, with a marker
+// wherever a backend's version changed between runs.
+fn draw_trend(path: &Path, case: &str, runs: &[Run]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut series: BTreeMap<String, Vec<(usize, f64)>> = BTreeMap::new();
+    for (i, run) in runs.iter().enumerate() {
+        for result in &run.results {
+            if result.case() == case {
+                series
+                    .entry(result.backend.clone())
+                    .or_default()
+                    .push((i, result.mean / 1000.0));
+            }
+        }
+    }
+    let max = series.values().flatten().map(|p| p.1).fold(0.0, f64::max);
+
+    let root = SVGBackend::new(path, (1280, 720)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(case, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d(0..runs.len().max(2) - 1, 0.0..max * 1.1)?;
+    chart
+        .configure_mesh()
+        .x_labels(runs.len())
+        .x_label_formatter(&|i| runs.get(*i).map(|r| r.label()).unwrap_or_default())
+        .x_desc("run")
+        .y_desc("mean time (µs)")
+        .draw()?;
+
+    for (i, window) in runs.windows(2).enumerate() {
+        let changed: Vec<String> = window[1]
+            .dependencies
+            .iter()
+            .filter(|(name, version)| window[0].dependencies.get(*name) != Some(version))
+            .map(|(name, version)| format!("{} {}", name, version))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        chart.draw_series(std::iter::once(PathElement::new(
+            vec![(i + 1, 0.0), (i + 1, max * 1.1)],
+            BLACK.mix(0.3),
+        )))?;
+        chart.draw_series(std::iter::once(Text::new(
+            changed.join(", "),
+            (i + 1, max * 1.05),
+            ("sans-serif", 14),
+        )))?;
+    }
+
+    for (b, (backend, points)) in series.iter().enumerate() {
+        let color = Palette99::pick(b);
+        let (r, g, b) = color.rgb();
+        let style = RGBColor(r, g, b);
+        chart
+            .draw_series(LineSeries::new(points.clone(), style.stroke_width(2)))?
+            .label(backend.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+        chart.draw_series(points.iter().map(|p| Circle::new(*p, 3, style.filled())))?;
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use tag_bench::backends;
+use tag_bench::corpus::corpus;
+use tag_bench::lossless::{self, Verdict};
+
+// Reads every file with one of the tag backends, checks its spectrum for
+// the low pass of a lossy encoder and prints the verdict.
+//
+// usage: lossless [--reader NAME] [FILE...]
+//
+// Without files the corpus from `TAG_BENCH_CORPUS` is checked.
+
+pub fn main() {
+    let mut reader_name = "metaflac".to_string();
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--reader" => {
+                reader_name = args.next().unwrap_or_else(|| {
+                    eprintln!("missing value for {}", arg);
+                    std::process::exit(2);
+                })
+            }
+            _ => files.push(arg),
+        }
+    }
+    let reader = backends::reader(&reader_name).unwrap_or_else(|| {
+        eprintln!("unknown reader {}", reader_name);
+        std::process::exit(2);
+    });
+    if files.is_empty() {
+        files = corpus();
+    }
+
+    let mut suspicious = 0;
+    for path in &files {
+        let mut metadata = reader(path.clone());
+        if let Err(err) = lossless::analyse(&mut metadata) {
+            println!("error        {}: {}", path, err);
+            continue;
+        }
+        let check = metadata.lossless_check.unwrap();
+        let label = match check.verdict {
+            Verdict::Lossless => "lossless",
+            Verdict::LossyTranscode => "transcode",
+            Verdict::Upsampled => "upsampled",
+            Verdict::Inconclusive => "inconclusive",
+        };
+        if !metadata.lossless {
+            suspicious += 1;
+        }
+        match check.cutoff_hz {
+            Some(cutoff) => println!(
+                "{:<12} {}: cutoff {} Hz, {}% confidence",
+                label, path, cutoff, check.confidence
+            ),
+            None => println!("{:<12} {}: {}% confidence", label, path, check.confidence),
+        }
+    }
+    println!("{} of {} files are not lossless", suspicious, files.len());
+}
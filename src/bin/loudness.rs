@@ -0,0 +1,106 @@
+use tag_bench::corpus::corpus;
+use tag_bench::decode::Decoder;
+use tag_bench::gain::{self, ReplayGain};
+use tag_bench::loudness::{Loudness, Meter};
+
+// Decodes every file, measures it per EBU R128 and reports files whose
+// tagged ReplayGain track gain or peak is off by more than the threshold.
+// Album gain is not checked, it depends on which files form the album.
+//
+// usage: loudness [--threshold DB] [FILE...]
+//
+// Without files the corpus from `TAG_BENCH_CORPUS` is analysed.
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value for {}", arg);
+        std::process::exit(2);
+    })
+}
+
+fn analyse(path: &str) -> Result<(Loudness, ReplayGain), Box<dyn std::error::Error>> {
+    let mut decoder = Decoder::open(path)?;
+    let mut meter = Meter::new(decoder.sample_rate(), decoder.channels());
+    decoder.for_each_interleaved(|samples| meter.add_interleaved(samples))?;
+    let mut tagged = ReplayGain::from_tags(&decoder.tags);
+    tagged.output_gain = gain::opus_output_gain(path)?;
+    Ok((meter.finish(), tagged))
+}
+
+pub fn main() {
+    let mut threshold = 1.0;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => {
+                let value = args.next().unwrap_or_else(|| {
+                    eprintln!("missing value for {}", arg);
+                    std::process::exit(2);
+                });
+                threshold = parse(&arg, &value);
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        files = corpus();
+    }
+
+    let mut deviating = 0;
+    for path in &files {
+        let (loudness, tagged) = match analyse(path) {
+            Ok(result) => result,
+            Err(err) => {
+                println!("error     {}: {}", path, err);
+                continue;
+            }
+        };
+        println!(
+            "{}: {:.1} LUFS, range {:.1} LU, true peak {:.1} dBTP",
+            path,
+            loudness.integrated,
+            loudness.range,
+            loudness.true_peak_dbtp()
+        );
+        for (key, value) in &tagged.malformed {
+            println!("malformed {}: {} = {:?}", path, key, value);
+        }
+
+        let mut problems = Vec::new();
+        match tagged.track_gain {
+            // The output gain is applied before the tagged gain.
+            Some(track_gain) => {
+                let expected = loudness.replay_gain() - tagged.output_gain.unwrap_or_default();
+                if (track_gain - expected).abs() > threshold {
+                    problems.push(format!(
+                        "track gain {:+.2} dB, measured {:+.2} dB",
+                        track_gain, expected
+                    ));
+                }
+            }
+            None => problems.push("no track gain".to_string()),
+        }
+        if let Some(track_peak) = tagged.track_peak {
+            // Tagged peaks are often sample peaks, so either one matching is
+            // fine.
+            let off = |peak: f64| (20.0 * (track_peak / peak).log10()).abs() > threshold;
+            if off(loudness.true_peak) && off(loudness.sample_peak) {
+                problems.push(format!(
+                    "track peak {:.6}, measured {:.6} (sample {:.6})",
+                    track_peak, loudness.true_peak, loudness.sample_peak
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            deviating += 1;
+            println!("deviates  {}: {}", path, problems.join(", "));
+        }
+    }
+    println!(
+        "{} of {} files deviate by more than {} dB",
+        deviating,
+        files.len(),
+        threshold
+    );
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tag_bench::backends::{self, READERS};
+use tag_bench::corpus::corpus;
+use tag_bench::memory;
+use tag_bench::stats;
+
+// Measures resident memory per backend. Every backend runs in its own child
+// process so allocations from one cannot hide behind another's peak.
+//
+// usage: memory [--files N] [--backend NAME]
+//
+// The corpus is taken from `TAG_BENCH_CORPUS`, see `tag_bench::corpus`.
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Report {
+    backend: String,
+    files: usize,
+    // Resident memory before the first file was read.
+    start_rss_kb: u64,
+    // Resident memory after the scan, what a long running scanner keeps.
+    end_rss_kb: u64,
+    // Peak resident memory during the scan.
+    scan_peak_kb: u64,
+    // Peak growth over the RSS before reading a single file.
+    file_peak_median_kb: f64,
+    file_peak_max_kb: f64,
+}
+
+pub fn main() {
+    let mut files = 10_000;
+    let mut only = None;
+    let mut child = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| {
+            eprintln!("missing value for {}", arg);
+            std::process::exit(2);
+        });
+        match arg.as_str() {
+            "--files" => {
+                files = value.parse().unwrap_or_else(|_| {
+                    eprintln!("--files expects a number");
+                    std::process::exit(2);
+                })
+            }
+            "--backend" => only = Some(value),
+            "--child" => child = Some(value),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(backend) = child {
+        let report = measure(&backend, files);
+        println!("{}", serde_json::to_string(&report).unwrap());
+        return;
+    }
+
+    if memory::status().is_none() {
+        eprintln!("/proc/self/status is not available, memory can only be measured on Linux");
+        std::process::exit(1);
+    }
+    let exe = std::env::current_exe().expect("failed to find own executable");
+    println!(
+        "{:<24} {:>10} {:>10} {:>10} {:>10} {:>12} {:>12}",
+        "backend", "files", "start kB", "end kB", "peak kB", "file med kB", "file max kB"
+    );
+    for (name, _) in READERS {
+        if only.as_deref().is_some_and(|only| only != name) {
+            continue;
+        }
+        let output = Command::new(&exe)
+            .args(["--child", name, "--files", &files.to_string()])
+            .output()
+            .expect("failed to run child process");
+        if !output.status.success() {
+            println!(
+                "{:<24} failed: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            continue;
+        }
+        let report: Report = serde_json::from_slice(&output.stdout).expect("invalid child report");
+        println!(
+            "{:<24} {:>10} {:>10} {:>10} {:>10} {:>12.0} {:>12.0}",
+            report.backend,
+            report.files,
+            report.start_rss_kb,
+            report.end_rss_kb,
+            report.scan_peak_kb,
+            report.file_peak_median_kb,
+            report.file_peak_max_kb,
+        );
+    }
+}
+
+fn measure(backend: &str, files: usize) -> Report {
+    let reader = backends::reader(backend).unwrap_or_else(|| {
+        eprintln!("unknown backend {}", backend);
+        std::process::exit(2);
+    });
+    let corpus = corpus();
+    if corpus.is_empty() {
+        eprintln!("corpus is empty");
+        std::process::exit(1);
+    }
+
+    // A scan of `files` files, cycling through the corpus when it is smaller.
+    let start = memory::status().expect("failed to read memory status");
+    memory::reset_peak();
+    for path in corpus.iter().cycle().take(files) {
+        reader(path.clone());
+    }
+    let end = memory::status().expect("failed to read memory status");
+
+    // Then the peak of every single file on its own.
+    let mut file_peaks: Vec<f64> = corpus
+        .iter()
+        .map(|path| {
+            memory::reset_peak();
+            let before = memory::status().expect("failed to read memory status");
+            reader(path.clone());
+            let after = memory::status().expect("failed to read memory status");
+            after.peak_kb.saturating_sub(before.rss_kb) as f64
+        })
+        .collect();
+    file_peaks.sort_by(|a, b| a.total_cmp(b));
+
+    Report {
+        backend: backend.to_string(),
+        files,
+        start_rss_kb: start.rss_kb,
+        end_rss_kb: end.rss_kb,
+        scan_peak_kb: end.peak_kb,
+        file_peak_median_kb: stats::percentile(&file_peaks, 0.5),
+        file_peak_max_kb: file_peaks.last().copied().unwrap_or_default(),
+    }
+}
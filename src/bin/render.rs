@@ -0,0 +1,169 @@
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tag_bench::waveform::{self, Spectrogram, Waveform};
+
+// Renders a min/max waveform and a spectrogram of an audio file, for player
+// previews and for looking at fixtures.
+//
+// usage: render FILE [--out DIR] [--format png|svg] [--width N] [--height N]
+//
+// Writes NAME_waveform.png (or .svg) and NAME_spectrogram.png to the output
+// directory, the spectrogram is always a PNG.
+
+const FFT_SIZE: usize = 2048;
+// Spectrogram colours run from this level to 0 dBFS.
+const FLOOR_DB: f64 = -120.0;
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value for {}", arg);
+        std::process::exit(2);
+    })
+}
+
+pub fn main() {
+    let mut file = None;
+    let mut out_dir = PathBuf::from(".");
+    let mut svg = false;
+    let mut width: u32 = 1800;
+    let mut height: u32 = 280;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            file = Some(arg);
+            continue;
+        }
+        let value = args.next().unwrap_or_else(|| {
+            eprintln!("missing value for {}", arg);
+            std::process::exit(2);
+        });
+        match arg.as_str() {
+            "--out" => out_dir = PathBuf::from(value),
+            "--format" => match value.as_str() {
+                "png" => svg = false,
+                "svg" => svg = true,
+                _ => {
+                    eprintln!("--format expects png or svg");
+                    std::process::exit(2);
+                }
+            },
+            "--width" => width = parse(&arg, &value),
+            "--height" => height = parse(&arg, &value),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+    let file = file.unwrap_or_else(|| {
+        eprintln!("usage: render FILE [--out DIR] [--format png|svg] [--width N] [--height N]");
+        std::process::exit(2);
+    });
+    // With the extension, so files that differ only in it do not overwrite
+    // each other's images.
+    let stem = Path::new(&file)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "audio".to_string());
+    fs::create_dir_all(&out_dir).expect("failed to create output directory");
+
+    let wave = waveform::waveform(&file, width as usize).expect("failed to decode file");
+    let path = out_dir.join(format!(
+        "{}_waveform.{}",
+        stem,
+        if svg { "svg" } else { "png" }
+    ));
+    if svg {
+        let root = SVGBackend::new(&path, (width, height)).into_drawing_area();
+        draw_waveform(&root, &wave).expect("failed to draw waveform");
+    } else {
+        let root = BitMapBackend::new(&path, (width, height)).into_drawing_area();
+        draw_waveform(&root, &wave).expect("failed to draw waveform");
+    }
+    println!("wrote {}", path.display());
+
+    let spectrogram =
+        waveform::spectrogram(&file, FFT_SIZE, width as usize).expect("failed to decode file");
+    let path = out_dir.join(format!("{}_spectrogram.png", stem));
+    let root = BitMapBackend::new(&path, (width, height * 2)).into_drawing_area();
+    draw_spectrogram(&root, &spectrogram).expect("failed to draw spectrogram");
+    println!("wrote {}", path.display());
+}
+
+fn draw_waveform<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    wave: &Waveform,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+    let seconds = wave.frames as f64 / wave.sample_rate.max(1) as f64;
+    let mut chart = ChartBuilder::on(root)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0.0..seconds, -1.0..1.0)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_desc("seconds")
+        .draw()?;
+
+    let step = seconds / wave.min.len().max(1) as f64;
+    let color = RGBColor(0x30, 0x6e, 0xd8);
+    chart.draw_series(
+        wave.min
+            .iter()
+            .zip(&wave.max)
+            .enumerate()
+            .map(|(i, (min, max))| {
+                let x = i as f64 * step;
+                PathElement::new(vec![(x, *min as f64), (x, *max as f64)], color)
+            }),
+    )?;
+    root.present()?;
+    Ok(())
+}
+
+// Dark blue through green and yellow to pale red as the level rises.
+fn heat(level: f64) -> HSLColor {
+    let v = level.clamp(0.0, 1.0);
+    HSLColor(0.66 - 0.66 * v, 1.0, 0.05 + 0.9 * v * v)
+}
+
+fn draw_spectrogram<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    spectrogram: &Spectrogram,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&BLACK)?;
+    let seconds = spectrogram.frames as f64 / spectrogram.sample_rate.max(1) as f64;
+    let nyquist = spectrogram.sample_rate as f64 / 2.0;
+    let mut chart = ChartBuilder::on(root)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..seconds, 0.0..nyquist / 1000.0)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .label_style(("sans-serif", 12).into_font().color(&WHITE))
+        .axis_style(WHITE)
+        .x_desc("seconds")
+        .y_desc("kHz")
+        .draw()?;
+
+    let columns = spectrogram.columns.len().max(1);
+    let step = seconds / columns as f64;
+    for (i, column) in spectrogram.columns.iter().enumerate() {
+        let bin_khz = nyquist / 1000.0 / (column.len() - 1) as f64;
+        let x = i as f64 * step;
+        chart.draw_series(column.iter().enumerate().map(|(bin, db)| {
+            let y = bin as f64 * bin_khz;
+            let color = heat((db - FLOOR_DB) / -FLOOR_DB);
+            Rectangle::new([(x, y), (x + step, y + bin_khz)], color.filled())
+        }))?;
+    }
+    root.present()?;
+    Ok(())
+}
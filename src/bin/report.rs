@@ -0,0 +1,356 @@
+use plotters::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tag_bench::results::{self, Benchmark, Throughput};
+
+// Draws comparison charts from criterion's estimates and links them from a
+// summary page.
+//
+// usage: report [criterion dir] [output dir]
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+
+pub fn main() {
+    let mut args = std::env::args().skip(1);
+    let criterion_dir = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(results::default_dir);
+    let out_dir = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| criterion_dir.join("summary"));
+
+    let benchmarks =
+        results::load_benchmarks(&criterion_dir).expect("failed to read criterion results");
+    if benchmarks.is_empty() {
+        eprintln!("no benchmarks found in {}", criterion_dir.display());
+        std::process::exit(1);
+    }
+    fs::create_dir_all(&out_dir).expect("failed to create output directory");
+
+    let backends = backends(&benchmarks);
+    let mut charts = Vec::new();
+
+    let (scaling, cases): (Vec<&Benchmark>, Vec<&Benchmark>) = benchmarks
+        .iter()
+        .partition(|b| b.value.as_deref().is_some_and(|v| v.parse::<f64>().is_ok()));
+
+    if !cases.is_empty() {
+        let name = "bars.svg".to_string();
+        draw_bars(&out_dir.join(&name), &cases, &backends).expect("failed to draw bar chart");
+        charts.push(("Mean time per case".to_string(), name));
+    }
+
+    let mut groups: BTreeMap<&str, Vec<&Benchmark>> = BTreeMap::new();
+    for benchmark in scaling {
+        groups.entry(&benchmark.group).or_default().push(benchmark);
+    }
+    for (i, (group, benchmarks)) in groups.iter().enumerate() {
+        let name = format!("scaling_{}.svg", i);
+        draw_scaling(&out_dir.join(&name), group, benchmarks, &backends)
+            .expect("failed to draw scaling chart");
+        charts.push((format!("{} scaling", group), name));
+    }
+
+    let name = "speedup.svg".to_string();
+    if draw_heatmap(&out_dir.join(&name), &benchmarks, &backends).expect("failed to draw heatmap") {
+        charts.push(("Slowdown relative to the fastest backend".to_string(), name));
+    }
+
+    write_index(&out_dir.join("index.html"), &charts, &benchmarks)
+        .expect("failed to write summary");
+    println!("wrote {}", out_dir.join("index.html").display());
+}
+
+fn backends(benchmarks: &[Benchmark]) -> Vec<String> {
+    let mut backends: Vec<String> = benchmarks.iter().map(|b| b.backend().to_string()).collect();
+    backends.sort();
+    backends.dedup();
+    backends
+}
+
+fn color(backends: &[String], backend: &str) -> RGBColor {
+    let index = backends.iter().position(|b| b == backend).unwrap_or(0);
+    let (r, g, b) = Palette99::pick(index).rgb();
+    RGBColor(r, g, b)
+}
+
+fn micros(nanos: f64) -> f64 {
+    nanos / 1000.0
+}
+
+// One cluster of bars per case, one bar per backend.
+fn draw_bars(
+    path: &Path,
+    benchmarks: &[&Benchmark],
+    backends: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cases: BTreeMap<String, Vec<&Benchmark>> = BTreeMap::new();
+    for benchmark in benchmarks {
+        cases.entry(benchmark.case()).or_default().push(benchmark);
+    }
+    let slots = backends.len() + 1;
+    let labels: Vec<&String> = cases.keys().collect();
+    let max = benchmarks
+        .iter()
+        .map(|b| micros(b.estimates.mean.confidence_interval.upper_bound))
+        .fold(0.0, f64::max);
+
+    let root = SVGBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Mean time per case", ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d((0..cases.len() * slots).into_segmented(), 0.0..max * 1.1)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(cases.len() * slots)
+        .x_label_formatter(&|v| match v {
+            SegmentValue::CenterOf(slot) if slot % slots == backends.len() / 2 => labels
+                .get(slot / slots)
+                .map(|l| l.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        })
+        .y_desc("time (µs)")
+        .draw()?;
+
+    for (b, backend) in backends.iter().enumerate() {
+        let style = color(backends, backend);
+        let bars = cases.values().enumerate().filter_map(|(c, case)| {
+            let benchmark = case.iter().find(|bench| bench.backend() == backend)?;
+            let slot = c * slots + b;
+            Some(Rectangle::new(
+                [
+                    (SegmentValue::Exact(slot), 0.0),
+                    (
+                        SegmentValue::Exact(slot + 1),
+                        micros(benchmark.estimates.mean.point_estimate),
+                    ),
+                ],
+                style.filled(),
+            ))
+        });
+        chart
+            .draw_series(bars)?
+            .label(backend.as_str())
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], style.filled()));
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+// Throughput (or time when none is set) against the benchmark parameter.
+fn draw_scaling(
+    path: &Path,
+    group: &str,
+    benchmarks: &[&Benchmark],
+    backends: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let point = |b: &Benchmark| -> (f64, f64) {
+        let x = b
+            .value
+            .as_deref()
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(1.0);
+        let seconds = b.estimates.mean.point_estimate / 1e9;
+        let y = match b.throughput {
+            Some(Throughput::Bytes(bytes)) => bytes as f64 / seconds / (1024.0 * 1024.0),
+            Some(Throughput::Elements(elements)) => elements as f64 / seconds,
+            None => micros(b.estimates.mean.point_estimate),
+        };
+        (x, y)
+    };
+    let y_desc = match benchmarks[0].throughput {
+        Some(Throughput::Bytes(_)) => "throughput (MiB/s)",
+        Some(Throughput::Elements(_)) => "throughput (elements/s)",
+        None => "time (µs)",
+    };
+    let points: Vec<(f64, f64)> = benchmarks.iter().map(|b| point(b)).collect();
+    let x_min = points
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::INFINITY, f64::min)
+        .max(1e-9);
+    let x_max = points
+        .iter()
+        .map(|p| p.0)
+        .fold(0.0, f64::max)
+        .max(x_min * 10.0);
+    let y_min = points
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::INFINITY, f64::min)
+        .max(1e-9);
+    let y_max = points
+        .iter()
+        .map(|p| p.1)
+        .fold(0.0, f64::max)
+        .max(y_min * 10.0);
+
+    let root = SVGBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(group, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d((x_min..x_max).log_scale(), (y_min..y_max).log_scale())?;
+    chart
+        .configure_mesh()
+        .x_desc("parameter")
+        .y_desc(y_desc)
+        .draw()?;
+
+    for backend in backends {
+        let mut series: Vec<(f64, f64)> = benchmarks
+            .iter()
+            .filter(|b| b.backend() == backend)
+            .map(|b| point(b))
+            .collect();
+        if series.is_empty() {
+            continue;
+        }
+        series.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let style = color(backends, backend);
+        chart
+            .draw_series(LineSeries::new(series.clone(), style.stroke_width(2)))?
+            .label(backend.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+        chart.draw_series(
+            series
+                .into_iter()
+                .map(|p| Circle::new(p, 3, style.filled())),
+        )?;
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+// Each cell is the backend's mean divided by the fastest mean of the case,
+// so the fastest backend reads 1.00x.
+fn draw_heatmap(
+    path: &Path,
+    benchmarks: &[Benchmark],
+    backends: &[String],
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut cases: BTreeMap<String, Vec<&Benchmark>> = BTreeMap::new();
+    for benchmark in benchmarks {
+        cases.entry(benchmark.case()).or_default().push(benchmark);
+    }
+    cases.retain(|_, case| case.len() > 1);
+    if cases.is_empty() {
+        return Ok(false);
+    }
+    let labels: Vec<&String> = cases.keys().collect();
+
+    let root = SVGBackend::new(path, (WIDTH, HEIGHT.max(120 + 40 * backends.len() as u32)))
+        .into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            "Slowdown relative to the fastest backend",
+            ("sans-serif", 24),
+        )
+        .margin(20)
+        .x_label_area_size(60)
+        .y_label_area_size(180)
+        .build_cartesian_2d(
+            (0..cases.len()).into_segmented(),
+            (0..backends.len()).into_segmented(),
+        )?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_labels(cases.len())
+        .y_labels(backends.len())
+        .x_label_formatter(&|v| match v {
+            SegmentValue::CenterOf(c) => labels.get(*c).map(|l| l.to_string()).unwrap_or_default(),
+            _ => String::new(),
+        })
+        .y_label_formatter(&|v| match v {
+            SegmentValue::CenterOf(b) => backends.get(*b).cloned().unwrap_or_default(),
+            _ => String::new(),
+        })
+        .draw()?;
+
+    for (c, case) in cases.values().enumerate() {
+        let fastest = case
+            .iter()
+            .map(|b| b.estimates.mean.point_estimate)
+            .fold(f64::INFINITY, f64::min);
+        for (b, backend) in backends.iter().enumerate() {
+            let cell = [
+                (SegmentValue::Exact(c), SegmentValue::Exact(b)),
+                (SegmentValue::Exact(c + 1), SegmentValue::Exact(b + 1)),
+            ];
+            let ratio = case
+                .iter()
+                .find(|bench| bench.backend() == backend)
+                .map(|bench| bench.estimates.mean.point_estimate / fastest);
+            let fill = match ratio {
+                // Green at 1x, red at 10x and beyond.
+                Some(ratio) => {
+                    let t = ratio.log10().clamp(0.0, 1.0);
+                    HSLColor((1.0 - t) / 3.0, 0.7, 0.6).filled()
+                }
+                None => RGBColor(230, 230, 230).filled(),
+            };
+            chart.draw_series(std::iter::once(Rectangle::new(cell, fill)))?;
+            if let Some(ratio) = ratio {
+                chart.draw_series(std::iter::once(Text::new(
+                    format!("{:.2}x", ratio),
+                    (SegmentValue::CenterOf(c), SegmentValue::CenterOf(b)),
+                    ("sans-serif", 16).into_font().color(&BLACK),
+                )))?;
+            }
+        }
+    }
+    root.present()?;
+    Ok(true)
+}
+
+fn write_index(
+    path: &Path,
+    charts: &[(String, String)],
+    benchmarks: &[Benchmark],
+) -> std::io::Result<()> {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Tag reader summary</title>\n</head>\n<body>\n<h1>Tag reader summary</h1>\n",
+    );
+    for (title, file) in charts {
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<img src=\"{}\" alt=\"{}\">\n",
+            title, file, title
+        ));
+    }
+    html.push_str("<h2>Estimates</h2>\n<table border=\"1\">\n<tr><th>benchmark</th><th>mean (µs)</th><th>median (µs)</th><th>std dev (µs)</th></tr>\n");
+    for benchmark in benchmarks {
+        let estimates = &benchmark.estimates;
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td></tr>\n",
+            benchmark.id(),
+            micros(estimates.mean.point_estimate),
+            micros(estimates.median.point_estimate),
+            micros(estimates.std_dev.point_estimate),
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    fs::write(path, html)
+}
@@ -0,0 +1,54 @@
+use tag_bench::corpus::corpus;
+use tag_bench::verify::{self, Verification};
+
+// Decodes every FLAC file and checks it against the MD5 in its STREAMINFO.
+// Exits with 1 when any file fails to verify.
+//
+// usage: verify [FILE...]
+//
+// Without files the corpus from `TAG_BENCH_CORPUS` is verified.
+
+pub fn main() {
+    let mut files: Vec<String> = std::env::args().skip(1).collect();
+    if files.is_empty() {
+        files = corpus();
+    }
+
+    let mut failed = 0;
+    let mut unset = 0;
+    for path in &files {
+        match verify::verify_flac(path) {
+            Ok(Verification::Match) => println!("ok        {}", path),
+            Ok(Verification::Mismatch { stored, computed }) => {
+                failed += 1;
+                println!(
+                    "MISMATCH  {}: stored {}, decoded {}",
+                    path,
+                    verify::hex(&stored),
+                    verify::hex(&computed)
+                );
+            }
+            Ok(Verification::Unset { computed }) => {
+                unset += 1;
+                println!(
+                    "unset     {}: no stored md5, decoded {}",
+                    path,
+                    verify::hex(&computed)
+                );
+            }
+            Err(err) => {
+                failed += 1;
+                println!("error     {}: {}", path, err);
+            }
+        }
+    }
+    println!(
+        "{} files, {} failed, {} without md5",
+        files.len(),
+        failed,
+        unset
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,44 @@
+use std::fs;
+use std::path::PathBuf;
+
+// The files scanned by the corpus benchmarks. `TAG_BENCH_CORPUS` points at a
+// directory of FLAC files, otherwise copies of the bundled fixture are used.
+pub fn corpus() -> Vec<String> {
+    let dir = match std::env::var("TAG_BENCH_CORPUS") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => {
+            let dir = std::env::temp_dir().join("tag_bench_corpus");
+            fs::create_dir_all(&dir).unwrap();
+            for i in 0..256 {
+                let copy = dir.join(format!("{:03}.flac", i));
+                if !copy.exists() {
+                    fs::copy("./full_test.flac", copy).unwrap();
+                }
+            }
+            dir
+        }
+    };
+    let mut files: Vec<String> = fs::read_dir(&dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+// The bundled fixtures, with the lossy formats `corpus` has none of.
+pub fn fixtures() -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir("./fixtures")
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
+use symphonia::core::codecs::{CodecParameters, Decoder as CodecDecoder, DecoderOptions};
+use symphonia::core::errors::{Error, Result};
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::backends::{merge_metadata, revisions, tag_key, Precedence};
+
+// Full decoding of a file's default track with symphonia, for the analyses
+// that look at the audio instead of the tags.
+
+pub struct Decoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+    track_id: u32,
+    pub params: CodecParameters,
+    // Raw tags from the container and from probing, keyed by upper-cased key.
+    pub tags: BTreeMap<String, Vec<String>>,
+    // The first buffer was decoded while opening and is still to be returned.
+    primed: bool,
+}
+
+impl Decoder {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Decoder> {
+        Decoder::open_with(path.as_ref(), true, false)
+    }
+
+    // Like `open`, with the encoder delay and padding symphonia finds in the
+    // stream headers trimmed from the decoded audio.
+    pub fn open_gapless<P: AsRef<Path>>(path: P) -> Result<Decoder> {
+        Decoder::open_with(path.as_ref(), true, true)
+    }
+
+    // Leaves `tags` empty. Symphonia still parses the metadata while probing,
+    // no reader honours the limits in `MetadataOptions`, only collecting it
+    // into `tags` is skipped.
+    pub fn open_audio_only<P: AsRef<Path>>(path: P) -> Result<Decoder> {
+        Decoder::open_with(path.as_ref(), false, false)
+    }
+
+    fn open_with(path: &Path, read_tags: bool, gapless: bool) -> Result<Decoder> {
+        let src = File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(src), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        let fmt_opts = FormatOptions {
+            enable_gapless: gapless,
+            ..Default::default()
+        };
+        let mut probed = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &fmt_opts,
+            &MetadataOptions::default(),
+        )?;
+
+        let mut tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if read_tags {
+            let container = revisions(probed.format.metadata());
+            let probe = probed.metadata.get().map(revisions).unwrap_or_default();
+            let merged = merge_metadata(&container, &probe, Precedence::Container);
+            for tag in merged.revision.tags() {
+                tags.entry(tag_key(tag))
+                    .or_default()
+                    .push(tag.value.to_string());
+            }
+        }
+
+        let track = probed
+            .format
+            .default_track()
+            .ok_or(Error::Unsupported("no audio track"))?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let decoder = symphonia::default::get_codecs().make(&params, &DecoderOptions::default())?;
+
+        let mut decoder = Decoder {
+            format: probed.format,
+            decoder,
+            track_id,
+            params,
+            tags,
+            primed: false,
+        };
+        // MP4 tracks leave the channel layout to the codec, so the first
+        // buffer is decoded up front and its spec fills in what the track
+        // does not say.
+        if decoder.params.channels.is_none() {
+            let spec = decoder.next_buffer()?.map(|buffer| *buffer.spec());
+            if let Some(spec) = spec {
+                decoder.params.channels = Some(spec.channels);
+                decoder.params.sample_rate.get_or_insert(spec.rate);
+                decoder.primed = true;
+            }
+        }
+        Ok(decoder)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.params.sample_rate.unwrap_or_default()
+    }
+
+    pub fn channels(&self) -> usize {
+        self.params.channels.map(|c| c.count()).unwrap_or_default()
+    }
+
+    // The next decoded buffer of the track, or None at the end of the stream.
+    // Packets that fail to decode are skipped, like symphonia's players do.
+    pub fn next_buffer(&mut self) -> Result<Option<AudioBufferRef<'_>>> {
+        if self.primed {
+            self.primed = false;
+            return Ok(Some(self.decoder.last_decoded()));
+        }
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(Error::ResetRequired) => return Ok(None),
+                Err(err) => return Err(err),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            match self.decoder.decode(&packet) {
+                Ok(_) => break,
+                Err(Error::DecodeError(_)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Some(self.decoder.last_decoded()))
+    }
+
+    // Calls `f` with interleaved f32 samples for every buffer until the end of
+    // the stream and returns the number of frames decoded.
+    pub fn for_each_interleaved<F: FnMut(&[f32])>(&mut self, mut f: F) -> Result<u64> {
+        let mut sample_buf: Option<SampleBuffer<f32>> = None;
+        let mut frames = 0;
+        while let Some(decoded) = self.next_buffer()? {
+            frames += decoded.frames() as u64;
+            let buf = sample_buf.get_or_insert_with(|| {
+                SampleBuffer::new(decoded.capacity() as u64, *decoded.spec())
+            });
+            if buf.capacity() < decoded.capacity() * decoded.spec().channels.count() {
+                *buf = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+            }
+            buf.copy_interleaved_ref(decoded);
+            f(buf.samples());
+        }
+        Ok(frames)
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use symphonia::core::errors::Result;
+
+use crate::decode::Decoder;
+use crate::gapless;
+use crate::stats;
+
+// Exact durations from a full decode, for checking the durations tag readers
+// estimate from headers, and the lengths some taggers store in tags.
+
+pub struct Decoded {
+    pub frames: u64,
+    pub sample_rate: u32,
+    pub tags: BTreeMap<String, Vec<String>>,
+}
+
+impl Decoded {
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+// Frames left after dropping the encoder delay and padding. Symphonia trims
+// what the stream headers declare while decoding, MP4 keeps them in the
+// iTunSMPB tag instead, which is subtracted here.
+pub fn decode<P: AsRef<Path>>(path: P) -> Result<Decoded> {
+    let mut decoder = Decoder::open_gapless(path)?;
+    let mut frames = decoder.for_each_interleaved(|_| ())?;
+    if gapless::from_codec_params(&decoder.params).is_none() {
+        if let Some(gapless) = gapless::from_extras(&decoder.tags) {
+            frames = frames.saturating_sub(gapless.delay as u64 + gapless.padding as u64);
+        }
+    }
+    Ok(Decoded {
+        frames,
+        sample_rate: decoder.sample_rate(),
+        tags: std::mem::take(&mut decoder.tags),
+    })
+}
+
+// The first length tag and its value in seconds: ID3 TLEN, or LENGTH or
+// DURATION as some Vorbis and MP4 taggers write them. Keys may carry a
+// prefix such as the MP4 freeform "----:com.apple.iTunes:".
+pub fn tagged_seconds(tags: &BTreeMap<String, Vec<String>>) -> Option<(String, f64)> {
+    tags.iter().find_map(|(key, values)| {
+        let name = key.rsplit(':').next().unwrap_or(key).trim();
+        if !["TLEN", "LENGTH", "DURATION"].contains(&name.to_ascii_uppercase().as_str()) {
+            return None;
+        }
+        values
+            .first()
+            .and_then(|value| parse_length(value))
+            .map(|seconds| (key.clone(), seconds))
+    })
+}
+
+// Whole numbers are milliseconds, as TLEN requires, numbers with a fraction
+// are seconds and "h:mm:ss" or "m:ss" are clock times.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(':') {
+        let mut seconds = 0.0;
+        for part in value.split(':') {
+            seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+        }
+        return Some(seconds);
+    }
+    if let Ok(ms) = value.parse::<u64>() {
+        return Some(ms as f64 / 1000.0);
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite() && *s >= 0.0)
+}
+
+// Errors of one source of durations, reported minus exact, in seconds.
+#[derive(Debug, Default, Clone)]
+pub struct ErrorSummary {
+    pub errors: Vec<f64>,
+    // Files the source gave no duration for.
+    pub missing: usize,
+    // Files the source failed to read.
+    pub failed: usize,
+}
+
+impl ErrorSummary {
+    pub fn add(&mut self, reported: Option<f64>, exact: f64) {
+        match reported {
+            Some(reported) => self.errors.push(reported - exact),
+            None => self.missing += 1,
+        }
+    }
+
+    fn sorted_abs(&self) -> Vec<f64> {
+        let mut abs: Vec<f64> = self.errors.iter().map(|e| e.abs()).collect();
+        abs.sort_by(f64::total_cmp);
+        abs
+    }
+
+    pub fn median_error(&self) -> f64 {
+        let mut errors = self.errors.clone();
+        errors.sort_by(f64::total_cmp);
+        stats::percentile(&errors, 0.5)
+    }
+
+    pub fn abs_percentile(&self, p: f64) -> f64 {
+        stats::percentile(&self.sorted_abs(), p)
+    }
+
+    pub fn max_abs(&self) -> f64 {
+        self.sorted_abs().last().copied().unwrap_or(f64::NAN)
+    }
+
+    pub fn over(&self, tolerance: f64) -> usize {
+        self.errors.iter().filter(|e| e.abs() > tolerance).count()
+    }
+}
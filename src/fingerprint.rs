@@ -0,0 +1,591 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::errors::{Error, Result};
+
+use crate::decode::Decoder;
+use crate::spectrum::{self, Framer};
+
+// Chromaprint fingerprints, as stored in ACOUSTID_FINGERPRINT tags and sent to
+// the AcoustID service. This follows Chromaprint's default algorithm: mono
+// audio at 11025 Hz, chroma features of overlapping frames and 16 classifiers
+// over the chroma image giving one 32-bit hash per frame. The resampler is
+// not Chromaprint's own, so hashes can differ in a few bits from `fpcalc`,
+// which `similarity` tolerates.
+
+// The algorithm id Chromaprint writes into the compressed fingerprint for
+// its default algorithm.
+pub const ALGORITHM: u8 = 1;
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_LENGTH: usize = 4096;
+const HOP: usize = FRAME_LENGTH / 3;
+// Like `fpcalc`, only the start of the file is fingerprinted.
+const MAX_SECONDS: u64 = 120;
+const MIN_FREQ_HZ: f64 = 28.0;
+const MAX_FREQ_HZ: f64 = 3520.0;
+const BANDS: usize = 12;
+const CHROMA_FILTER: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+// Chroma vectors with a smaller norm are silence and become all zeros.
+const MIN_NORM: f64 = 0.01;
+// Offsets, in hashes of about 0.12 s, tried when aligning two fingerprints.
+const MAX_OFFSET: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub algorithm: u8,
+    pub hashes: Vec<u32>,
+}
+
+impl Fingerprint {
+    // Chromaprint's compressed form in URL-safe base64 without padding, the
+    // format of ACOUSTID_FINGERPRINT tags.
+    pub fn encode(&self) -> String {
+        base64_encode(&compress(self))
+    }
+
+    pub fn decode(encoded: &str) -> Option<Fingerprint> {
+        decompress(&base64_decode(encoded.trim())?)
+    }
+
+    // Fraction of equal bits, 0.5 for unrelated audio and close to 1 for the
+    // same recording, at the best alignment of the two. The overlap must
+    // cover at least half of the shorter fingerprint.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let (a, b) = (&self.hashes, &other.hashes);
+        let min_overlap = (a.len().min(b.len()) / 2).max(1);
+        let mut best: f64 = 0.0;
+        for shift in -(MAX_OFFSET.min(b.len()) as isize)..=MAX_OFFSET.min(a.len()) as isize {
+            let (a, b) = if shift >= 0 {
+                (&a[shift as usize..], &b[..])
+            } else {
+                (&a[..], &b[(-shift) as usize..])
+            };
+            let overlap = a.len().min(b.len());
+            if overlap < min_overlap {
+                continue;
+            }
+            let errors: u32 = a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum();
+            best = best.max(1.0 - errors as f64 / (overlap * 32) as f64);
+        }
+        best
+    }
+}
+
+// The stored fingerprint from tags keyed like `AudioMetadata::extras`, under
+// the Vorbis, ID3 TXXX or MP4 freeform name.
+pub fn stored(extras: &BTreeMap<String, Vec<String>>) -> Option<Fingerprint> {
+    extras
+        .iter()
+        .find(|(key, _)| {
+            let key = key.rsplit(':').next().unwrap_or(key);
+            key.trim()
+                .replace(' ', "_")
+                .eq_ignore_ascii_case("ACOUSTID_FINGERPRINT")
+        })
+        .and_then(|(_, values)| values.first())
+        .and_then(|value| Fingerprint::decode(value))
+}
+
+pub fn fingerprint<P: AsRef<Path>>(path: P) -> Result<Fingerprint> {
+    let mut decoder = Decoder::open_audio_only(path)?;
+    let channels = match decoder.channels() {
+        0 => return Err(Error::Unsupported("unknown channel count")),
+        channels => channels,
+    };
+    let max_frames = decoder.sample_rate() as u64 * MAX_SECONDS;
+    let mut resampler = Resampler::new(decoder.sample_rate(), SAMPLE_RATE);
+    let mut framer = Framer::new(1, FRAME_LENGTH, HOP);
+    let mut chroma = Chroma::new();
+    let mut resampled = Vec::new();
+
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+    let mut frames = 0;
+    while frames < max_frames {
+        let decoded = match decoder.next_buffer()? {
+            Some(decoded) => decoded,
+            None => break,
+        };
+        let buf = sample_buf
+            .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        if buf.capacity() < decoded.capacity() * decoded.spec().channels.count() {
+            *buf = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        }
+        buf.copy_interleaved_ref(decoded);
+        let take = (max_frames - frames).min((buf.samples().len() / channels) as u64) as usize;
+        frames += take as u64;
+
+        resampled.clear();
+        for frame in buf.samples()[..take * channels].chunks_exact(channels) {
+            let sum: f32 = frame.iter().sum();
+            resampler.push(sum as f64 / channels as f64, &mut resampled);
+        }
+        framer.push(&resampled, |frame| chroma.add_frame(frame));
+    }
+
+    Ok(Fingerprint {
+        algorithm: ALGORITHM,
+        hashes: chroma.hashes(),
+    })
+}
+
+const KERNEL_STEPS: usize = 256;
+
+// Windowed sinc resampler for a mono stream, low passed at 80% of the lower
+// Nyquist frequency like Chromaprint's.
+struct Resampler {
+    step: f64,
+    half_taps: usize,
+    // The filter at `KERNEL_STEPS` points per input sample from its centre.
+    kernel: Vec<f64>,
+    input: Vec<f64>,
+    // Position of the next output sample in `input`.
+    position: f64,
+}
+
+impl Resampler {
+    fn new(from: u32, to: u32) -> Resampler {
+        let factor = (to as f64 * 0.8 / from.max(1) as f64).min(1.0);
+        let half_taps = (8.0 / factor).ceil() as usize;
+        let kernel = (0..=(half_taps + 1) * KERNEL_STEPS)
+            .map(|i| {
+                let t = i as f64 / KERNEL_STEPS as f64;
+                factor * sinc(factor * t) * kaiser(t / (half_taps as f64 + 1.0))
+            })
+            .collect();
+        Resampler {
+            step: from as f64 / to as f64,
+            half_taps,
+            kernel,
+            // Starts with half a filter of silence so the first output sample
+            // is centred on the first input sample.
+            input: vec![0.0; half_taps],
+            position: half_taps as f64,
+        }
+    }
+
+    fn push(&mut self, sample: f64, out: &mut Vec<f32>) {
+        self.input.push(sample);
+        while self.position + (self.half_taps as f64) < self.input.len() as f64 {
+            let centre = self.position.floor() as usize;
+            let mut sum = 0.0;
+            for k in centre + 1 - self.half_taps..=centre + self.half_taps {
+                let t = (self.position - k as f64).abs() * KERNEL_STEPS as f64;
+                let (i, fraction) = (t as usize, t.fract());
+                let weight = self.kernel[i] + (self.kernel[i + 1] - self.kernel[i]) * fraction;
+                sum += self.input[k] * weight;
+            }
+            out.push(sum as f32);
+            self.position += self.step;
+        }
+        // Drops the input no later output sample needs.
+        let used = (self.position.floor() as usize).saturating_sub(self.half_taps);
+        if used > 4096 {
+            self.input.drain(..used);
+            self.position -= used as f64;
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Kaiser window with beta 9, for `x` in -1..1.
+fn kaiser(x: f64) -> f64 {
+    const BETA: f64 = 9.0;
+    bessel_i0(BETA * (1.0 - x * x).max(0.0).sqrt()) / bessel_i0(BETA)
+}
+
+fn bessel_i0(x: f64) -> f64 {
+    let (mut sum, mut term) = (1.0, 1.0);
+    for k in 1..50 {
+        term *= (x / (2.0 * k as f64)).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+// Chroma features of each frame, smoothed over time and normalised, then
+// hashed once enough rows are in for the widest classifier.
+struct Chroma {
+    window: Vec<f64>,
+    // Pitch class of every FFT bin between the minimum and maximum frequency.
+    notes: Vec<(usize, usize)>,
+    history: Vec<[f64; BANDS]>,
+    rows: Vec<[f64; BANDS]>,
+}
+
+impl Chroma {
+    fn new() -> Chroma {
+        let bin_hz = SAMPLE_RATE as f64 / FRAME_LENGTH as f64;
+        let first = ((MIN_FREQ_HZ / bin_hz).round() as usize).max(1);
+        let last = ((MAX_FREQ_HZ / bin_hz).round() as usize).min(FRAME_LENGTH / 2);
+        let notes = (first..last)
+            .map(|bin| {
+                let octave = (bin as f64 * bin_hz / (440.0 / 16.0)).log2();
+                (bin, (BANDS as f64 * (octave - octave.floor())) as usize)
+            })
+            .collect();
+        let window = (0..FRAME_LENGTH)
+            .map(|n| 0.54 - 0.46 * (2.0 * PI * n as f64 / (FRAME_LENGTH - 1) as f64).cos())
+            .collect();
+        Chroma {
+            window,
+            notes,
+            history: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn add_frame(&mut self, frame: &[f64]) {
+        let power = spectrum::power_spectrum(frame, &self.window);
+        let mut features = [0.0; BANDS];
+        for &(bin, note) in &self.notes {
+            features[note] += power[bin];
+        }
+        self.history.push(features);
+        if self.history.len() < CHROMA_FILTER.len() {
+            return;
+        }
+
+        let mut row = [0.0; BANDS];
+        for (coefficient, features) in CHROMA_FILTER.iter().zip(&self.history) {
+            for (value, feature) in row.iter_mut().zip(features) {
+                *value += coefficient * feature;
+            }
+        }
+        self.history.remove(0);
+        let norm = row.iter().map(|v| v * v).sum::<f64>().sqrt();
+        for value in row.iter_mut() {
+            *value = if norm < MIN_NORM { 0.0 } else { *value / norm };
+        }
+        self.rows.push(row);
+    }
+
+    fn hashes(&self) -> Vec<u32> {
+        let image = IntegralImage::new(&self.rows);
+        let width = CLASSIFIERS.iter().map(|c| c.width).max().unwrap();
+        (0..(self.rows.len() + 1).saturating_sub(width))
+            .map(|x| {
+                CLASSIFIERS.iter().fold(0, |hash, classifier| {
+                    (hash << 2) | gray_code(classifier.classify(&image, x))
+                })
+            })
+            .collect()
+    }
+}
+
+// Sums over rectangles of the chroma image, rows are frames and columns are
+// pitch classes.
+struct IntegralImage {
+    sums: Vec<[f64; BANDS + 1]>,
+}
+
+impl IntegralImage {
+    fn new(rows: &[[f64; BANDS]]) -> IntegralImage {
+        let mut sums = vec![[0.0; BANDS + 1]];
+        for row in rows {
+            let above = *sums.last().unwrap();
+            let mut next = [0.0; BANDS + 1];
+            let mut running = 0.0;
+            for band in 0..BANDS {
+                running += row[band];
+                next[band + 1] = above[band + 1] + running;
+            }
+            sums.push(next);
+        }
+        IntegralImage { sums }
+    }
+
+    // Sum of rows `x1..x2` and columns `y1..y2`.
+    fn area(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> f64 {
+        self.sums[x2][y2] - self.sums[x1][y2] - self.sums[x2][y1] + self.sums[x1][y1]
+    }
+}
+
+struct Classifier {
+    filter: u8,
+    y: usize,
+    height: usize,
+    width: usize,
+    thresholds: [f64; 3],
+}
+
+impl Classifier {
+    // Compares the filter's halves or thirds of the `width` x `height`
+    // rectangle at row `x` and quantises the result to 0..4.
+    fn classify(&self, image: &IntegralImage, x: usize) -> u32 {
+        let (y, w, h) = (self.y, self.width, self.height);
+        let area = |x1, y1, x2, y2| image.area(x + x1, y + y1, x + x2, y + y2);
+        let (a, b) = match self.filter {
+            0 => (area(0, 0, w, h), 0.0),
+            1 => (area(0, h / 2, w, h), area(0, 0, w, h / 2)),
+            2 => (area(w / 2, 0, w, h), area(0, 0, w / 2, h)),
+            3 => (
+                area(0, h / 2, w / 2, h) + area(w / 2, 0, w, h / 2),
+                area(0, 0, w / 2, h / 2) + area(w / 2, h / 2, w, h),
+            ),
+            4 => (
+                area(0, h / 3, w, h / 3 * 2),
+                area(0, 0, w, h / 3) + area(0, h / 3 * 2, w, h),
+            ),
+            _ => (
+                area(w / 3, 0, w / 3 * 2, h),
+                area(0, 0, w / 3, h) + area(w / 3 * 2, 0, w, h),
+            ),
+        };
+        let value = (1.0 + a).ln() - (1.0 + b).ln();
+        self.thresholds.iter().filter(|t| value >= **t).count() as u32
+    }
+}
+
+const fn classifier(filter: u8, y: usize, height: usize, width: usize, t: [f64; 3]) -> Classifier {
+    Classifier {
+        filter,
+        y,
+        height,
+        width,
+        thresholds: t,
+    }
+}
+
+// Chromaprint's trained classifiers for its default algorithm.
+const CLASSIFIERS: [Classifier; 16] = [
+    classifier(0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    classifier(4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    classifier(1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    classifier(3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    classifier(3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    classifier(4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    classifier(1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    classifier(2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    classifier(2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    classifier(2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    classifier(5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    classifier(3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    classifier(2, 1, 1, 14, [-0.101475, 0.0225617, 0.231971]),
+    classifier(3, 5, 6, 4, [-0.0799915, -0.00729616, 0.063262]),
+    classifier(1, 9, 2, 12, [-0.272556, 0.019424, 0.302559]),
+    classifier(3, 4, 2, 14, [-0.164292, -0.0321188, 0.08463]),
+];
+
+fn gray_code(value: u32) -> u32 {
+    [0, 1, 3, 2][value as usize]
+}
+
+// Chromaprint's compression: a four byte header with the algorithm and the
+// hash count, then the positions of the bits that changed from the previous
+// hash as gaps packed into 3 bits, with gaps of 7 or more continued in a
+// second array of 5 bit values.
+fn compress(fingerprint: &Fingerprint) -> Vec<u8> {
+    let count = fingerprint.hashes.len() as u32;
+    let mut gaps = Vec::new();
+    let mut previous = 0;
+    for &hash in &fingerprint.hashes {
+        let mut changed = hash ^ previous;
+        let mut last_bit = 0;
+        let mut bit = 1;
+        while changed != 0 {
+            if changed & 1 != 0 {
+                gaps.push(bit - last_bit);
+                last_bit = bit;
+            }
+            changed >>= 1;
+            bit += 1;
+        }
+        gaps.push(0);
+        previous = hash;
+    }
+
+    let mut out = vec![
+        fingerprint.algorithm,
+        (count >> 16) as u8,
+        (count >> 8) as u8,
+        count as u8,
+    ];
+    let mut writer = BitWriter::default();
+    for &gap in &gaps {
+        writer.write(gap.min(7), 3);
+    }
+    out.extend(writer.finish());
+    let mut writer = BitWriter::default();
+    for &gap in gaps.iter().filter(|gap| **gap >= 7) {
+        writer.write(gap - 7, 5);
+    }
+    out.extend(writer.finish());
+    out
+}
+
+fn decompress(data: &[u8]) -> Option<Fingerprint> {
+    let (header, body) = (data.get(..4)?, &data[4..]);
+    let count = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+
+    let mut reader = BitReader::new(body);
+    let mut gaps = Vec::new();
+    let mut ends = 0;
+    while ends < count {
+        let gap = reader.read(3)?;
+        if gap == 0 {
+            ends += 1;
+        }
+        gaps.push(gap);
+    }
+    let mut reader = BitReader::new(&body[(gaps.len() * 3).div_ceil(8)..]);
+    for gap in gaps.iter_mut().filter(|gap| **gap == 7) {
+        *gap += reader.read(5)?;
+    }
+
+    let mut hashes = Vec::with_capacity(count);
+    let (mut hash, mut last_bit) = (0u32, 0);
+    let mut previous = 0;
+    for gap in gaps {
+        if gap == 0 {
+            previous ^= hash;
+            hashes.push(previous);
+            (hash, last_bit) = (0, 0);
+            continue;
+        }
+        last_bit += gap;
+        if last_bit > 32 {
+            return None;
+        }
+        hash |= 1 << (last_bit - 1);
+    }
+    Some(Fingerprint {
+        algorithm: header[0],
+        hashes,
+    })
+}
+
+// Packs values least significant bit first, the order Chromaprint uses.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, width: usize) {
+        for i in 0..width {
+            if self.bits / 8 == self.bytes.len() {
+                self.bytes.push(0);
+            }
+            if value >> i & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 1 << (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> BitReader<'a> {
+        BitReader { bytes, bits: 0 }
+    }
+
+    fn read(&mut self, width: usize) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..width {
+            let byte = self.bytes.get(self.bits / 8)?;
+            value |= ((byte >> (self.bits % 8)) as u32 & 1) << i;
+            self.bits += 1;
+        }
+        Some(value)
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+// Also accepts the standard alphabet and padding.
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in encoded.bytes().filter(|c| *c != b'=') {
+        let value = match c {
+            b'+' => 62,
+            b'/' => 63,
+            _ => BASE64.iter().position(|b| *b == c)? as u32,
+        };
+        n = n << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_round_trip() {
+        // Single high bits need gaps of 7 or more and use the second array.
+        let fingerprint = Fingerprint {
+            algorithm: ALGORITHM,
+            hashes: vec![
+                0,
+                1,
+                0x8000_0000,
+                0xdead_beef,
+                0xdead_beef,
+                u32::MAX,
+                0x1234_5678,
+            ],
+        };
+        assert_eq!(
+            decompress(&compress(&fingerprint)),
+            Some(fingerprint.clone())
+        );
+        assert_eq!(
+            Fingerprint::decode(&fingerprint.encode()),
+            Some(fingerprint)
+        );
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        for len in (0..8).chain([data.len()]) {
+            assert_eq!(
+                base64_decode(&base64_encode(&data[..len])).unwrap(),
+                &data[..len]
+            );
+        }
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "-_8");
+        assert_eq!(base64_decode("+/8=").unwrap(), [0xfb, 0xff]);
+    }
+}
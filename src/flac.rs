@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Minimal FLAC metadata reader used as a lower bound for the general purpose
+// libraries. Only STREAMINFO and VORBIS_COMMENT are decoded, every other block
+// is skipped by seeking past it.
+
+const BLOCK_STREAMINFO: u8 = 0;
+const BLOCK_VORBIS_COMMENT: u8 = 4;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidInput(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::InvalidInput(msg) => write!(f, "invalid flac: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct StreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub num_channels: u8,
+    pub bits_per_sample: u8,
+    pub total_samples: u64,
+    pub md5: [u8; 16],
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct VorbisComment {
+    pub vendor_string: String,
+    // Keys are upper-cased, Vorbis comment field names are case-insensitive.
+    pub comments: HashMap<String, Vec<String>>,
+}
+
+impl VorbisComment {
+    pub fn get(&self, key: &str) -> Option<&Vec<String>> {
+        self.comments.get(key)
+    }
+
+    pub fn title(&self) -> Option<&Vec<String>> {
+        self.get("TITLE")
+    }
+
+    pub fn artist(&self) -> Option<&Vec<String>> {
+        self.get("ARTIST")
+    }
+
+    pub fn album(&self) -> Option<&Vec<String>> {
+        self.get("ALBUM")
+    }
+
+    pub fn album_artist(&self) -> Option<&Vec<String>> {
+        self.get("ALBUMARTIST")
+    }
+
+    pub fn track(&self) -> Option<u32> {
+        self.get("TRACKNUMBER")
+            .and_then(|v| v[0].parse::<u32>().ok())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Tag {
+    pub stream_info: Option<StreamInfo>,
+    pub vorbis_comment: Option<VorbisComment>,
+}
+
+impl Tag {
+    pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Tag, Error> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        Tag::read_from(&mut reader)
+    }
+
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Tag, Error> {
+        let mut tag = Tag::default();
+
+        let mut ident = [0u8; 4];
+        reader.read_exact(&mut ident)?;
+        if &ident[..3] == b"ID3" {
+            skip_id3v2(reader, ident[3])?;
+            reader.read_exact(&mut ident)?;
+        }
+        if &ident != b"fLaC" {
+            return Err(Error::InvalidInput("missing fLaC marker"));
+        }
+
+        loop {
+            let mut header = [0u8; 4];
+            reader.read_exact(&mut header)?;
+            let is_last = header[0] & 0x80 != 0;
+            let block_type = header[0] & 0x7f;
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+
+            match block_type {
+                BLOCK_STREAMINFO => {
+                    let mut data = vec![0u8; length as usize];
+                    reader.read_exact(&mut data)?;
+                    tag.stream_info = Some(read_stream_info(&data)?);
+                }
+                BLOCK_VORBIS_COMMENT => {
+                    let mut data = vec![0u8; length as usize];
+                    reader.read_exact(&mut data)?;
+                    tag.vorbis_comment = Some(read_vorbis_comment(&data)?);
+                }
+                127 => return Err(Error::InvalidInput("invalid metadata block type")),
+                // PADDING, PICTURE, SEEKTABLE, APPLICATION, CUESHEET and
+                // reserved blocks are never decoded.
+                _ => {
+                    reader.seek(SeekFrom::Current(length as i64))?;
+                }
+            }
+
+            if is_last {
+                break;
+            }
+        }
+
+        Ok(tag)
+    }
+
+    pub fn vorbis_comments(&self) -> Option<&VorbisComment> {
+        self.vorbis_comment.as_ref()
+    }
+}
+
+fn skip_id3v2<R: Read + Seek>(reader: &mut R, version: u8) -> Result<(), Error> {
+    // Remaining ID3v2 header: revision, flags and a 28 bit syncsafe size.
+    let mut header = [0u8; 6];
+    reader.read_exact(&mut header)?;
+    if version == 0xff || header[0] == 0xff {
+        return Err(Error::InvalidInput("invalid id3v2 header"));
+    }
+    let size = header[2..]
+        .iter()
+        .fold(0u32, |acc, b| (acc << 7) | (*b & 0x7f) as u32);
+    let footer = if header[1] & 0x10 != 0 { 10 } else { 0 };
+    reader.seek(SeekFrom::Current(size as i64 + footer))?;
+    Ok(())
+}
+
+fn read_stream_info(data: &[u8]) -> Result<StreamInfo, Error> {
+    if data.len() < 34 {
+        return Err(Error::InvalidInput("streaminfo block too short"));
+    }
+    let packed = u64::from_be_bytes([
+        data[10], data[11], data[12], data[13], data[14], data[15], data[16], data[17],
+    ]);
+    let mut md5 = [0u8; 16];
+    md5.copy_from_slice(&data[18..34]);
+
+    Ok(StreamInfo {
+        min_block_size: u16::from_be_bytes([data[0], data[1]]),
+        max_block_size: u16::from_be_bytes([data[2], data[3]]),
+        min_frame_size: u32::from_be_bytes([0, data[4], data[5], data[6]]),
+        max_frame_size: u32::from_be_bytes([0, data[7], data[8], data[9]]),
+        sample_rate: (packed >> 44) as u32,
+        num_channels: ((packed >> 41) & 0x07) as u8 + 1,
+        bits_per_sample: ((packed >> 36) & 0x1f) as u8 + 1,
+        total_samples: packed & 0x0f_ffff_ffff,
+        md5,
+    })
+}
+
+fn read_vorbis_comment(data: &[u8]) -> Result<VorbisComment, Error> {
+    let mut cursor = data;
+    let vendor_length = read_u32_le(&mut cursor)? as usize;
+    let vendor_string = String::from_utf8_lossy(take(&mut cursor, vendor_length)?).into_owned();
+
+    let num_comments = read_u32_le(&mut cursor)?;
+    let mut comments: HashMap<String, Vec<String>> = HashMap::new();
+    for _ in 0..num_comments {
+        let length = read_u32_le(&mut cursor)? as usize;
+        let comment = take(&mut cursor, length)?;
+        let comment = String::from_utf8_lossy(comment);
+        if let Some((key, value)) = comment.split_once('=') {
+            comments
+                .entry(key.to_ascii_uppercase())
+                .or_default()
+                .push(value.to_owned());
+        }
+    }
+
+    Ok(VorbisComment {
+        vendor_string,
+        comments,
+    })
+}
+
+fn read_u32_le(cursor: &mut &[u8]) -> Result<u32, Error> {
+    let bytes = take(cursor, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn take<'a>(cursor: &mut &'a [u8], length: usize) -> Result<&'a [u8], Error> {
+    if cursor.len() < length {
+        return Err(Error::InvalidInput("vorbis comment block truncated"));
+    }
+    let (head, tail) = cursor.split_at(length);
+    *cursor = tail;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{get_metadata_flac_baseline, get_metadata_metaflac};
+
+    #[test]
+    fn matches_metaflac() {
+        let path = "full_test.flac";
+        let tag = Tag::read_from_path(path).unwrap();
+        let reference = metaflac::Tag::read_from_path(path).unwrap();
+
+        let stream_info = tag.stream_info.as_ref().unwrap();
+        let expected = reference.get_streaminfo().unwrap();
+        assert_eq!(stream_info.sample_rate, expected.sample_rate);
+        assert_eq!(stream_info.num_channels, expected.num_channels);
+        assert_eq!(stream_info.bits_per_sample, expected.bits_per_sample);
+        assert_eq!(stream_info.total_samples, expected.total_samples);
+        assert_eq!(stream_info.md5[..], expected.md5[..]);
+
+        let comments = &tag.vorbis_comment.as_ref().unwrap().comments;
+        let expected = &reference.vorbis_comments().unwrap().comments;
+        assert_eq!(comments, expected);
+
+        assert_eq!(
+            get_metadata_flac_baseline(path.to_string()),
+            get_metadata_metaflac(path.to_string())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+// ReplayGain and Opus R128 gain read from raw tags. Gains are in dB relative
+// to the ReplayGain reference of -18 LUFS, peaks are linear sample values.
+//
+// Keys are matched after stripping the ID3v2 `TXXX:` and the MP4 freeform
+// `----:com.apple.iTunes:` prefixes, so Vorbis comments, ID3 user text frames
+// and iTunes atoms all end up in the same fields.
+
+// Gains outside of this range are treated as malformed.
+const MAX_GAIN_DB: f64 = 64.0;
+// R128 gains are stored relative to -23 LUFS, ReplayGain to -18 LUFS.
+const R128_TO_REPLAYGAIN_DB: f64 = 5.0;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    // The OpusHead output gain in dB, applied by every Opus decoder on top of
+    // the gains above.
+    pub output_gain: Option<f64>,
+    // Raw keys and values that were recognised but could not be parsed.
+    pub malformed: Vec<(String, String)>,
+}
+
+impl ReplayGain {
+    // Reads the gain tags from raw keys as kept in `AudioMetadata::extras`.
+    pub fn from_tags<'a, I>(tags: I) -> ReplayGain
+    where
+        I: IntoIterator<Item = (&'a String, &'a Vec<String>)>,
+    {
+        let mut gain = ReplayGain::default();
+        let mut r128_track = None;
+        let mut r128_album = None;
+        for (raw_key, values) in tags {
+            let value = match values.first() {
+                Some(value) => value,
+                None => continue,
+            };
+            let parsed = match normalize_key(raw_key).as_str() {
+                "REPLAYGAIN_TRACK_GAIN" => parse_gain(value).map(|v| gain.track_gain = Some(v)),
+                "REPLAYGAIN_TRACK_PEAK" => parse_peak(value).map(|v| gain.track_peak = Some(v)),
+                "REPLAYGAIN_ALBUM_GAIN" => parse_gain(value).map(|v| gain.album_gain = Some(v)),
+                "REPLAYGAIN_ALBUM_PEAK" => parse_peak(value).map(|v| gain.album_peak = Some(v)),
+                "R128_TRACK_GAIN" => parse_r128(value).map(|v| r128_track = Some(v)),
+                "R128_ALBUM_GAIN" => parse_r128(value).map(|v| r128_album = Some(v)),
+                _ => continue,
+            };
+            if parsed.is_none() {
+                gain.malformed.push((raw_key.clone(), value.clone()));
+            }
+        }
+        // Explicit ReplayGain tags win over the Opus ones.
+        gain.track_gain = gain.track_gain.or(r128_track);
+        gain.album_gain = gain.album_gain.or(r128_album);
+        gain
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.track_gain.is_none()
+            && self.track_peak.is_none()
+            && self.album_gain.is_none()
+            && self.album_peak.is_none()
+            && self.output_gain.is_none()
+    }
+}
+
+// Upper-cased key without the ID3v2 `TXXX:` or MP4 `----:mean:` prefix.
+fn normalize_key(key: &str) -> String {
+    let key = key.rsplit(':').next().unwrap_or(key);
+    key.trim().to_ascii_uppercase()
+}
+
+// "-6.54 dB", "+2.1 dB" or a bare number.
+fn parse_gain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = match value.len().checked_sub(2) {
+        Some(end) if value.is_char_boundary(end) && value[end..].eq_ignore_ascii_case("db") => {
+            value[..end].trim_end()
+        }
+        _ => value,
+    };
+    let gain: f64 = value.strip_prefix('+').unwrap_or(value).parse().ok()?;
+    (gain.is_finite() && gain.abs() <= MAX_GAIN_DB).then_some(gain)
+}
+
+fn parse_peak(value: &str) -> Option<f64> {
+    let peak: f64 = value.trim().parse().ok()?;
+    (peak.is_finite() && peak >= 0.0).then_some(peak)
+}
+
+// A signed Q7.8 fixed point number of dB relative to -23 LUFS.
+fn parse_r128(value: &str) -> Option<f64> {
+    let q78: i16 = value.trim().parse().ok()?;
+    Some(q78 as f64 / 256.0 + R128_TO_REPLAYGAIN_DB)
+}
+
+// The output gain from the OpusHead packet of an Ogg Opus file, or None when
+// the file is not Opus.
+pub fn opus_output_gain<P: AsRef<Path>>(path: P) -> io::Result<Option<f64>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    // The first page of an Ogg stream holds exactly the identification header.
+    let mut header = [0u8; 27];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != b"OggS" {
+        return Ok(None);
+    }
+    let mut segments = vec![0u8; header[26] as usize];
+    reader.read_exact(&mut segments)?;
+
+    let mut packet = [0u8; 19];
+    if segments.iter().map(|s| *s as usize).sum::<usize>() < packet.len() {
+        return Ok(None);
+    }
+    reader.read_exact(&mut packet)?;
+    if &packet[..8] != b"OpusHead" {
+        return Ok(None);
+    }
+    let q78 = i16::from_le_bytes([packet[16], packet[17]]);
+    Ok(Some(q78 as f64 / 256.0))
+}
+
+// Gains of a file from its tags and, for Opus, its header.
+pub fn read_replay_gain<P: AsRef<Path>>(
+    path: P,
+    extras: &BTreeMap<String, Vec<String>>,
+) -> io::Result<ReplayGain> {
+    let mut gain = ReplayGain::from_tags(extras);
+    gain.output_gain = opus_output_gain(path)?;
+    Ok(gain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gains() {
+        assert_eq!(parse_gain("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_gain("+2.1 dB"), Some(2.1));
+        assert_eq!(parse_gain(" 3.5db "), Some(3.5));
+        assert_eq!(parse_gain("-0.5"), Some(-0.5));
+        assert_eq!(parse_gain("dB"), None);
+        assert_eq!(parse_gain("loud"), None);
+        assert_eq!(parse_gain("NaN dB"), None);
+        assert_eq!(parse_gain("-100 dB"), None);
+    }
+
+    #[test]
+    fn r128_gains() {
+        // Q7.8 dB relative to -23 LUFS, 5 dB below the ReplayGain reference.
+        assert_eq!(parse_r128("0"), Some(5.0));
+        assert_eq!(parse_r128("-256"), Some(4.0));
+        assert_eq!(parse_r128(" 384 "), Some(6.5));
+        assert_eq!(parse_r128("40000"), None);
+        assert_eq!(parse_r128("1.5"), None);
+    }
+
+    #[test]
+    fn replaygain_tags_win_over_r128() {
+        let tags: BTreeMap<String, Vec<String>> = [
+            ("R128_TRACK_GAIN", "-256"),
+            ("R128_ALBUM_GAIN", "-512"),
+            ("TXXX:REPLAYGAIN_TRACK_GAIN", "-6.00 dB"),
+            ("REPLAYGAIN_TRACK_PEAK", "high"),
+        ]
+        .iter()
+        .map(|(key, value)| (key.to_string(), vec![value.to_string()]))
+        .collect();
+        let gain = ReplayGain::from_tags(&tags);
+        assert_eq!(gain.track_gain, Some(-6.0));
+        assert_eq!(gain.album_gain, Some(3.0));
+        assert_eq!(
+            gain.malformed,
+            [("REPLAYGAIN_TRACK_PEAK".to_string(), "high".to_string())]
+        );
+    }
+}
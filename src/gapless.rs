@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use symphonia::core::codecs::{
+    CodecParameters, CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS,
+};
+
+// Encoder delay and padding, the frames a player drops from the start and end
+// of the decoded stream for gapless playback.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GaplessSource {
+    // The LAME extension of the Xing/Info header in the first MP3 frame.
+    LameHeader,
+    // The iTunSMPB tag iTunes and most AAC encoders write.
+    ITunSmpb,
+    // The pre-skip in the Opus identification header, padding from the
+    // granule position of the last page.
+    OpusPreSkip,
+    // The granule positions of the first and last Ogg pages, for Vorbis and
+    // FLAC in Ogg.
+    OggGranule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gapless {
+    pub delay: u32,
+    pub padding: u32,
+    pub source: GaplessSource,
+}
+
+// What symphonia found in the container or stream headers of a track, for the
+// codecs it is known to fill in delay and padding for. MP4 is not covered,
+// symphonia does not read iTunSMPB, see `from_extras`.
+pub fn from_codec_params(params: &CodecParameters) -> Option<Gapless> {
+    if params.delay.is_none() && params.padding.is_none() {
+        return None;
+    }
+    let source = match params.codec {
+        CODEC_TYPE_MP3 => GaplessSource::LameHeader,
+        CODEC_TYPE_OPUS => GaplessSource::OpusPreSkip,
+        // Native FLAC has neither, so values for FLAC come from Ogg.
+        CODEC_TYPE_VORBIS | CODEC_TYPE_FLAC => GaplessSource::OggGranule,
+        _ => return None,
+    };
+    Some(Gapless {
+        delay: params.delay.unwrap_or_default(),
+        padding: params.padding.unwrap_or_default(),
+        source,
+    })
+}
+
+// iTunSMPB from tags keyed like `AudioMetadata::extras`, under its ID3 TXXX
+// or MP4 freeform name. This is all the lofty backends report: neither lofty
+// 0.11 nor 0.21 exposes the LAME header's delay and padding or the Opus
+// pre-skip, so their MP3 and Opus files come back without gapless values.
+pub fn from_extras(extras: &BTreeMap<String, Vec<String>>) -> Option<Gapless> {
+    extras
+        .iter()
+        .find(|(key, _)| key.rsplit(':').next().unwrap_or(key).trim() == "ITUNSMPB")
+        .and_then(|(_, values)| values.first())
+        .and_then(|value| parse_itunsmpb(value))
+}
+
+// Space separated hex fields: a reserved zero, the delay, the padding and the
+// original length, then fields nobody documents.
+fn parse_itunsmpb(value: &str) -> Option<Gapless> {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    let field = |i: usize| fields.get(i).and_then(|f| u32::from_str_radix(f, 16).ok());
+    Some(Gapless {
+        delay: field(1)?,
+        padding: field(2)?,
+        source: GaplessSource::ITunSmpb,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia::core::codecs::CODEC_TYPE_AAC;
+
+    #[test]
+    fn itunsmpb() {
+        let value = " 00000000 00000840 00000338 0000000000015888 00000000 00000000";
+        assert_eq!(
+            parse_itunsmpb(value),
+            Some(Gapless {
+                delay: 2112,
+                padding: 824,
+                source: GaplessSource::ITunSmpb,
+            })
+        );
+        assert_eq!(parse_itunsmpb("00000000 00000840"), None);
+        assert_eq!(parse_itunsmpb("00000000 0000084G 00000338"), None);
+        assert_eq!(parse_itunsmpb(""), None);
+
+        let extras = BTreeMap::from([(
+            "----:COM.APPLE.ITUNES:ITUNSMPB".to_string(),
+            vec![value.to_string()],
+        )]);
+        assert_eq!(from_extras(&extras).map(|g| g.delay), Some(2112));
+    }
+
+    #[test]
+    fn codec_params_of_unknown_codecs() {
+        let mut params = CodecParameters::new();
+        params.with_delay(2112).with_padding(824);
+        assert_eq!(from_codec_params(params.for_codec(CODEC_TYPE_AAC)), None);
+        assert_eq!(
+            from_codec_params(params.for_codec(CODEC_TYPE_VORBIS)).map(|g| g.source),
+            Some(GaplessSource::OggGranule)
+        );
+    }
+}
@@ -0,0 +1,16 @@
+pub mod backends;
+pub mod corpus;
+pub mod decode;
+pub mod duration;
+pub mod fingerprint;
+pub mod flac;
+pub mod gain;
+pub mod gapless;
+pub mod lossless;
+pub mod loudness;
+pub mod memory;
+pub mod results;
+pub mod spectrum;
+pub mod stats;
+pub mod verify;
+pub mod waveform;
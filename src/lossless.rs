@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use crate::backends::AudioMetadata;
+use crate::decode::Decoder;
+use crate::spectrum::{self, Framer};
+
+// Spots lossless files transcoded from lossy sources. Lossy encoders low pass
+// the audio well below Nyquist, which shows up in the averaged spectrum as a
+// steep drop to the noise floor at a fixed frequency.
+
+const FRAME_LENGTH: usize = 4096;
+// Frames quieter than this are left out of the average.
+const SILENCE_DB: f64 = -60.0;
+// Width of the bands compared on either side of a candidate cutoff.
+const BAND_HZ: f64 = 1000.0;
+const LOWEST_CUTOFF_HZ: f64 = 10_000.0;
+// Drops below this are ordinary roll-off, above `CERTAIN_DROP_DB` a brick wall.
+const MIN_DROP_DB: f64 = 15.0;
+const CERTAIN_DROP_DB: f64 = 45.0;
+// A cutoff needs audio below it no quieter than this under the loudest band,
+// otherwise the drop is between two levels of noise.
+const CONTENT_RANGE_DB: f64 = 60.0;
+// Cutoffs closer to Nyquist than this are left by anti-aliasing filters.
+const NYQUIST_MARGIN: f64 = 0.95;
+// Typical low pass frequencies of MP3, AAC and Vorbis encoders by bitrate.
+const ENCODER_CUTOFFS_HZ: [f64; 8] = [
+    11_000.0, 15_000.0, 16_000.0, 17_000.0, 18_000.0, 19_000.0, 19_500.0, 20_000.0,
+];
+const ENCODER_CUTOFF_TOLERANCE_HZ: f64 = 300.0;
+// On high sample rate files, cutoffs between these come from a 44.1 or 48 kHz
+// source rather than from a lossy encoder.
+const UPSAMPLED_CUTOFF_HZ: (f64, f64) = (20_500.0, 24_300.0);
+const HIGH_RES_NYQUIST_HZ: f64 = 30_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Lossless,
+    // Low passed like a lossy encoder does, at `cutoff_hz`.
+    LossyTranscode,
+    // A high sample rate file cut off at the Nyquist frequency of 44.1 or 48 kHz.
+    Upsampled,
+    // Too little audio, or none in the range where encoders cut off.
+    Inconclusive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LosslessCheck {
+    pub verdict: Verdict,
+    // 0 to 100.
+    pub confidence: u8,
+    pub cutoff_hz: Option<u32>,
+}
+
+// Decodes `metadata.path`, stores the check and clears `lossless` for files
+// that are not what they claim to be.
+pub fn analyse(metadata: &mut AudioMetadata) -> Result<(), Box<dyn std::error::Error>> {
+    let check = check(&metadata.path)?;
+    if matches!(check.verdict, Verdict::LossyTranscode | Verdict::Upsampled) {
+        metadata.lossless = false;
+    }
+    metadata.lossless_check = Some(check);
+    Ok(())
+}
+
+pub fn check<P: AsRef<Path>>(path: P) -> Result<LosslessCheck, Box<dyn std::error::Error>> {
+    let mut decoder = Decoder::open_audio_only(path)?;
+    let sample_rate = decoder.sample_rate() as f64;
+    let window = spectrum::hann(FRAME_LENGTH);
+    let mut framer = Framer::new(decoder.channels(), FRAME_LENGTH, FRAME_LENGTH);
+    let mut sum = vec![0.0; FRAME_LENGTH / 2 + 1];
+    let mut frames = 0;
+    decoder.for_each_interleaved(|samples| {
+        framer.push(samples, |frame| {
+            let mean_square = frame.iter().map(|x| x * x).sum::<f64>() / frame.len() as f64;
+            if spectrum::to_db(mean_square) < SILENCE_DB {
+                return;
+            }
+            for (total, power) in sum.iter_mut().zip(spectrum::power_spectrum(frame, &window)) {
+                *total += power;
+            }
+            frames += 1;
+        })
+    })?;
+    if frames == 0 || sample_rate == 0.0 {
+        return Ok(LosslessCheck {
+            verdict: Verdict::Inconclusive,
+            confidence: 0,
+            cutoff_hz: None,
+        });
+    }
+
+    let db: Vec<f64> = sum
+        .iter()
+        .map(|p| spectrum::to_db(p / frames as f64))
+        .collect();
+    Ok(classify(&db, sample_rate))
+}
+
+fn classify(db: &[f64], sample_rate: f64) -> LosslessCheck {
+    let nyquist = sample_rate / 2.0;
+    let bin_hz = nyquist / (db.len() - 1) as f64;
+    let band = (BAND_HZ / bin_hz).round().max(1.0) as usize;
+    let mean = |bins: &[f64]| bins.iter().sum::<f64>() / bins.len() as f64;
+
+    let loudest = (0..db.len().saturating_sub(band))
+        .map(|bin| mean(&db[bin..bin + band]))
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    // The candidate with the largest drop from the band below to the band
+    // above it.
+    let first = ((LOWEST_CUTOFF_HZ / bin_hz) as usize).max(band);
+    let candidate = (first..db.len().saturating_sub(band))
+        .map(|bin| (bin, mean(&db[bin - band..bin]), mean(&db[bin..bin + band])))
+        .filter(|(_, below, _)| *below >= loudest - CONTENT_RANGE_DB)
+        .map(|(bin, below, above)| (bin, below - above))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    let (cutoff, drop) = match candidate {
+        Some(candidate) => candidate,
+        None => {
+            return LosslessCheck {
+                verdict: Verdict::Inconclusive,
+                confidence: 0,
+                cutoff_hz: None,
+            }
+        }
+    };
+    let cutoff_hz = cutoff as f64 * bin_hz;
+
+    if drop < MIN_DROP_DB || cutoff_hz >= nyquist * NYQUIST_MARGIN {
+        let confidence = if drop < MIN_DROP_DB {
+            1.0 - drop.max(0.0) / MIN_DROP_DB * 0.5
+        } else {
+            0.6
+        };
+        return LosslessCheck {
+            verdict: Verdict::Lossless,
+            confidence: percent(confidence),
+            cutoff_hz: None,
+        };
+    }
+
+    let mut confidence = ((drop - MIN_DROP_DB) / (CERTAIN_DROP_DB - MIN_DROP_DB)).min(1.0) * 0.8;
+    let near_encoder_cutoff = ENCODER_CUTOFFS_HZ
+        .iter()
+        .any(|t| (cutoff_hz - t).abs() <= ENCODER_CUTOFF_TOLERANCE_HZ);
+    let (low, high) = UPSAMPLED_CUTOFF_HZ;
+    let verdict = if nyquist > HIGH_RES_NYQUIST_HZ && (low..=high).contains(&cutoff_hz) {
+        Verdict::Upsampled
+    } else {
+        Verdict::LossyTranscode
+    };
+    if verdict == Verdict::Upsampled || near_encoder_cutoff {
+        confidence += 0.2;
+    }
+    LosslessCheck {
+        verdict,
+        confidence: percent(confidence.max(0.5)),
+        cutoff_hz: Some(cutoff_hz.round() as u32),
+    }
+}
+
+fn percent(confidence: f64) -> u8 {
+    (confidence.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aac_is_never_lossless() {
+        let check = check("fixtures/tone.m4a").unwrap();
+        assert_ne!(check.verdict, Verdict::Lossless);
+    }
+}
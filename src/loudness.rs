@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+
+use crate::stats;
+
+// Loudness measurement following ITU-R BS.1770-4 and EBU Tech 3341/3342:
+// K-weighted mean square energy in 100 ms steps, gated integrated loudness
+// over 400 ms blocks, loudness range over 3 s windows and true peak from 4x
+// oversampled audio.
+
+const STEP_MS: u32 = 100;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+// Taps per phase of the true peak interpolation filter.
+const INTERPOLATION_TAPS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    // LUFS, negative infinity for silence.
+    pub integrated: f64,
+    // LU between the 10th and 95th percentile of short-term loudness.
+    pub range: f64,
+    // Linear, 1.0 is digital full scale.
+    pub true_peak: f64,
+    pub sample_peak: f64,
+}
+
+impl Loudness {
+    // The ReplayGain 2.0 track gain that brings this file to -18 LUFS.
+    pub fn replay_gain(&self) -> f64 {
+        -18.0 - self.integrated
+    }
+
+    pub fn true_peak_dbtp(&self) -> f64 {
+        20.0 * self.true_peak.log10()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+// The two stage K-weighting filter, a high shelf modelling the head followed
+// by a high pass, with coefficients derived for any sample rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+// Channel weights for the usual layouts, the LFE of 5.1 is not measured and
+// the surround channels count 1.5 dB more.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    if channels == 6 {
+        vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41]
+    } else {
+        vec![1.0; channels]
+    }
+}
+
+// Windowed sinc low pass split into `factor` phases of INTERPOLATION_TAPS
+// taps each, every phase has unity gain at DC.
+fn interpolation_filter(factor: usize) -> Vec<Vec<f64>> {
+    let length = factor * INTERPOLATION_TAPS;
+    let center = (length - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..length)
+        .map(|n| {
+            let x = (n as f64 - center) / factor as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f64 / (length - 1) as f64).cos();
+            sinc * window
+        })
+        .collect();
+    (0..factor)
+        .map(|phase| {
+            let phase_taps: Vec<f64> = taps.iter().skip(phase).step_by(factor).copied().collect();
+            let sum: f64 = phase_taps.iter().sum();
+            phase_taps.iter().map(|t| t / sum).collect()
+        })
+        .collect()
+}
+
+pub struct Meter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    step_frames: usize,
+    // Weighted sum of squares of the current step, and how many frames it has.
+    step_energy: f64,
+    step_filled: usize,
+    // Mean square energy of every completed step.
+    steps: Vec<f64>,
+    phases: Vec<Vec<f64>>,
+    // The last INTERPOLATION_TAPS samples per channel, newest last.
+    history: Vec<Vec<f64>>,
+    true_peak: f64,
+    sample_peak: f64,
+}
+
+impl Meter {
+    pub fn new(sample_rate: u32, channels: usize) -> Meter {
+        // Oversample to at least 192 kHz, as BS.1770 recommends.
+        let factor = match sample_rate {
+            0..=95_999 => 4,
+            96_000..=191_999 => 2,
+            _ => 1,
+        };
+        Meter {
+            channels,
+            weights: channel_weights(channels),
+            filters: vec![k_weighting(sample_rate); channels],
+            step_frames: (sample_rate * STEP_MS / 1000).max(1) as usize,
+            step_energy: 0.0,
+            step_filled: 0,
+            steps: Vec::new(),
+            phases: interpolation_filter(factor),
+            history: vec![vec![0.0; INTERPOLATION_TAPS]; channels],
+            true_peak: 0.0,
+            sample_peak: 0.0,
+        }
+    }
+
+    pub fn add_interleaved(&mut self, samples: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                let x = *sample as f64;
+                self.sample_peak = self.sample_peak.max(x.abs());
+                self.update_true_peak(channel, x);
+
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let y = high_pass.process(shelf.process(x));
+                self.step_energy += self.weights[channel] * y * y;
+            }
+            self.step_filled += 1;
+            if self.step_filled == self.step_frames {
+                self.steps.push(self.step_energy / self.step_frames as f64);
+                self.step_energy = 0.0;
+                self.step_filled = 0;
+            }
+        }
+    }
+
+    fn update_true_peak(&mut self, channel: usize, x: f64) {
+        let history = &mut self.history[channel];
+        history.rotate_left(1);
+        history[INTERPOLATION_TAPS - 1] = x;
+        for phase in &self.phases {
+            // Taps are applied newest sample first.
+            let y: f64 = phase
+                .iter()
+                .zip(history.iter().rev())
+                .map(|(t, s)| t * s)
+                .sum();
+            self.true_peak = self.true_peak.max(y.abs());
+        }
+    }
+
+    // Partial steps at the end are dropped, as are blocks that would need them.
+    pub fn finish(self) -> Loudness {
+        let momentary = block_energies(&self.steps, MOMENTARY_STEPS);
+        let short_term = block_energies(&self.steps, SHORT_TERM_STEPS);
+        Loudness {
+            integrated: integrated_loudness(&momentary),
+            range: loudness_range(&short_term),
+            true_peak: self.true_peak.max(self.sample_peak),
+            sample_peak: self.sample_peak,
+        }
+    }
+}
+
+// Mean energy of every window of `steps` consecutive steps, advancing one
+// step at a time.
+fn block_energies(steps: &[f64], length: usize) -> Vec<f64> {
+    steps
+        .windows(length)
+        .map(|window| window.iter().sum::<f64>() / length as f64)
+        .collect()
+}
+
+fn to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn gated_mean(energies: &[f64], gate: f64) -> Option<f64> {
+    let gated: Vec<f64> = energies
+        .iter()
+        .copied()
+        .filter(|e| to_lufs(*e) > gate)
+        .collect();
+    (!gated.is_empty()).then(|| stats::mean(&gated))
+}
+
+fn integrated_loudness(blocks: &[f64]) -> f64 {
+    let relative = match gated_mean(blocks, ABSOLUTE_GATE) {
+        Some(energy) => to_lufs(energy) + RELATIVE_GATE,
+        None => return f64::NEG_INFINITY,
+    };
+    match gated_mean(blocks, relative.max(ABSOLUTE_GATE)) {
+        Some(energy) => to_lufs(energy),
+        None => f64::NEG_INFINITY,
+    }
+}
+
+fn loudness_range(blocks: &[f64]) -> f64 {
+    let relative = match gated_mean(blocks, ABSOLUTE_GATE) {
+        Some(energy) => to_lufs(energy) + RANGE_RELATIVE_GATE,
+        None => return 0.0,
+    };
+    let gate = relative.max(ABSOLUTE_GATE);
+    let mut loudness: Vec<f64> = blocks
+        .iter()
+        .map(|e| to_lufs(*e))
+        .filter(|l| *l > gate)
+        .collect();
+    if loudness.is_empty() {
+        return 0.0;
+    }
+    loudness.sort_by(f64::total_cmp);
+    stats::percentile(&loudness, 0.95) - stats::percentile(&loudness, 0.10)
+}
@@ -0,0 +1,32 @@
+use std::fs;
+
+// Memory figures from `/proc/self/status`, all in kB. Linux only, every
+// function returns None or false elsewhere.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    // VmRSS, the current resident set size.
+    pub rss_kb: u64,
+    // VmHWM, the peak resident set size since start or the last reset.
+    pub peak_kb: u64,
+}
+
+pub fn status() -> Option<Status> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| {
+        status
+            .lines()
+            .find(|line| line.starts_with(name))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|kb| kb.parse::<u64>().ok())
+    };
+    Some(Status {
+        rss_kb: field("VmRSS:")?,
+        peak_kb: field("VmHWM:")?,
+    })
+}
+
+// Resets VmHWM to the current RSS, see clear_refs in proc(5).
+pub fn reset_peak() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Readers for the files criterion writes under `target/criterion`. Every
+// benchmark gets a `new` directory holding `benchmark.json` (its id) and
+// `estimates.json` (the statistics of the latest run).
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum Throughput {
+    Bytes(u64),
+    Elements(u64),
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub confidence_level: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub confidence_interval: ConfidenceInterval,
+    pub point_estimate: f64,
+    pub standard_error: f64,
+}
+
+// All times are in nanoseconds.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct Estimates {
+    pub mean: Estimate,
+    pub median: Estimate,
+    pub median_abs_dev: Estimate,
+    pub slope: Option<Estimate>,
+    pub std_dev: Estimate,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct BenchmarkId {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<Throughput>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub group: String,
+    pub function: String,
+    pub value: Option<String>,
+    pub throughput: Option<Throughput>,
+    pub estimates: Estimates,
+    pub directory: PathBuf,
+}
+
+impl Benchmark {
+    // `group/function/value`, matching the id criterion prints.
+    pub fn id(&self) -> String {
+        match &self.value {
+            Some(value) => format!("{}/{}/{}", self.group, self.function, value),
+            None => format!("{}/{}", self.group, self.function),
+        }
+    }
+
+    // The label used for the backend, without the quotes `stringify!` adds.
+    pub fn backend(&self) -> &str {
+        self.function.trim_matches('"')
+    }
+
+    // Groups and parameters together identify what was measured, so
+    // benchmarks sharing a case can be compared with each other.
+    pub fn case(&self) -> String {
+        match &self.value {
+            Some(value) => format!("{}/{}", self.group, value),
+            None => self.group.clone(),
+        }
+    }
+}
+
+pub fn default_dir() -> PathBuf {
+    match std::env::var("CARGO_TARGET_DIR") {
+        Ok(target) => Path::new(&target).join("criterion"),
+        Err(_) => PathBuf::from("target/criterion"),
+    }
+}
+
+pub fn load_benchmarks(root: &Path) -> io::Result<Vec<Benchmark>> {
+    let mut benchmarks = Vec::new();
+    visit(root, &mut benchmarks)?;
+    benchmarks.sort_by_key(|b| b.id());
+    Ok(benchmarks)
+}
+
+fn visit(dir: &Path, benchmarks: &mut Vec<Benchmark>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.file_name().is_some_and(|name| name == "new") {
+            if let Some(benchmark) = read_benchmark(&path)? {
+                benchmarks.push(benchmark);
+            }
+        } else {
+            visit(&path, benchmarks)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_benchmark(dir: &Path) -> io::Result<Option<Benchmark>> {
+    let id_path = dir.join("benchmark.json");
+    let estimates_path = dir.join("estimates.json");
+    if !id_path.exists() || !estimates_path.exists() {
+        return Ok(None);
+    }
+    let id: BenchmarkId = read_json(&id_path)?;
+    let estimates: Estimates = read_json(&estimates_path)?;
+    Ok(Some(Benchmark {
+        group: id.group_id,
+        function: id.function_id.unwrap_or_default(),
+        value: id.value_str,
+        throughput: id.throughput,
+        estimates,
+        directory: dir.to_path_buf(),
+    }))
+}
+
+pub fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let data = fs::read(path)?;
+    serde_json::from_slice(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// A flattened row per benchmark, as exported and stored as a baseline.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Summary {
+    pub id: String,
+    pub group: String,
+    pub backend: String,
+    pub value: Option<String>,
+    pub mean: f64,
+    pub mean_lower: f64,
+    pub mean_upper: f64,
+    pub median: f64,
+    pub median_lower: f64,
+    pub median_upper: f64,
+    pub confidence_level: f64,
+}
+
+impl From<&Benchmark> for Summary {
+    fn from(benchmark: &Benchmark) -> Summary {
+        let mean = &benchmark.estimates.mean;
+        let median = &benchmark.estimates.median;
+        Summary {
+            id: benchmark.id(),
+            group: benchmark.group.clone(),
+            backend: benchmark.backend().to_string(),
+            value: benchmark.value.clone(),
+            mean: mean.point_estimate,
+            mean_lower: mean.confidence_interval.lower_bound,
+            mean_upper: mean.confidence_interval.upper_bound,
+            median: median.point_estimate,
+            median_lower: median.confidence_interval.lower_bound,
+            median_upper: median.confidence_interval.upper_bound,
+            confidence_level: mean.confidence_interval.confidence_level,
+        }
+    }
+}
+
+impl Summary {
+    pub fn case(&self) -> String {
+        match &self.value {
+            Some(value) => format!("{}/{}", self.group, value),
+            None => self.group.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Sample {
+    iters: Vec<f64>,
+    times: Vec<f64>,
+}
+
+// The raw measurements of the latest run as nanoseconds per iteration.
+pub fn load_samples(benchmark: &Benchmark) -> io::Result<Vec<f64>> {
+    let sample: Sample = read_json(&benchmark.directory.join("sample.json"))?;
+    Ok(sample
+        .times
+        .iter()
+        .zip(&sample.iters)
+        .map(|(time, iters)| time / iters)
+        .collect())
+}
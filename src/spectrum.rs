@@ -0,0 +1,108 @@
+use std::f64::consts::PI;
+
+// Just enough FFT for the spectral analyses: power spectra of real,
+// Hann-windowed frames whose length is a power of two.
+
+pub fn hann(length: usize) -> Vec<f64> {
+    (0..length)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / length as f64).cos())
+        .collect()
+}
+
+// In-place iterative radix-2 FFT, `re` and `im` must have the same power of
+// two length.
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    assert!(n.is_power_of_two() && im.len() == n, "fft length");
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f64;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let a = start + k;
+                let b = a + length / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+// Squared magnitudes of the first `frame.len() / 2 + 1` bins of the windowed
+// frame.
+pub fn power_spectrum(frame: &[f64], window: &[f64]) -> Vec<f64> {
+    let mut re: Vec<f64> = frame.iter().zip(window).map(|(x, w)| x * w).collect();
+    let mut im = vec![0.0; re.len()];
+    fft(&mut re, &mut im);
+    re.iter()
+        .zip(&im)
+        .take(frame.len() / 2 + 1)
+        .map(|(re, im)| re * re + im * im)
+        .collect()
+}
+
+pub fn to_db(power: f64) -> f64 {
+    10.0 * power.max(1e-20).log10()
+}
+
+// Splits interleaved audio into frames of `length` mono samples, the mean of
+// all channels, advancing by `hop` samples. Samples are buffered across calls
+// so decoded buffers of any size can be fed in.
+pub struct Framer {
+    channels: usize,
+    length: usize,
+    hop: usize,
+    buffer: Vec<f64>,
+    // Samples still to drop before the next frame starts, when `hop` is
+    // longer than a frame.
+    skip: usize,
+}
+
+impl Framer {
+    pub fn new(channels: usize, length: usize, hop: usize) -> Framer {
+        Framer {
+            channels: channels.max(1),
+            length,
+            hop,
+            buffer: Vec::with_capacity(length * 2),
+            skip: 0,
+        }
+    }
+
+    pub fn push<F: FnMut(&[f64])>(&mut self, samples: &[f32], mut f: F) {
+        for frame in samples.chunks_exact(self.channels) {
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            let sum: f32 = frame.iter().sum();
+            self.buffer.push(sum as f64 / self.channels as f64);
+            if self.buffer.len() == self.length {
+                f(&self.buffer);
+                self.buffer.drain(..self.hop.min(self.length));
+                self.skip = self.hop.saturating_sub(self.length);
+            }
+        }
+    }
+}
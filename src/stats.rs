@@ -0,0 +1,121 @@
+// Small statistics helpers for comparing benchmark samples. Nothing here
+// assumes the samples are normally distributed.
+
+// SplitMix64, good enough for bootstrap resampling and reproducible.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Linear interpolation between closest ranks, `p` in [0, 1].
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p * (sorted.len() - 1) as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+// Two-sided Mann-Whitney U test using the normal approximation with tie
+// correction. Returns U for `a` and the p-value.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let n1 = a.len() as f64;
+    let n2 = b.len() as f64;
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|v| (*v, true))
+        .chain(b.iter().map(|v| (*v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j + 1 < all.len() && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        // Tied values share the average of their ranks.
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        let ties = (j - i + 1) as f64;
+        tie_term += ties * ties * ties - ties;
+        rank_sum_a += all[i..=j].iter().filter(|v| v.1).count() as f64 * rank;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let mean_u = n1 * n2 / 2.0;
+    let sd_u = (n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)))).sqrt();
+    if sd_u == 0.0 {
+        return (u, 1.0);
+    }
+    // Continuity correction towards the mean.
+    let z = ((u - mean_u).abs() - 0.5).max(0.0) / sd_u;
+    (u, erfc(z / std::f64::consts::SQRT_2))
+}
+
+// Percentile bootstrap confidence interval for mean(a) / mean(b).
+pub fn bootstrap_ratio_ci(
+    a: &[f64],
+    b: &[f64],
+    resamples: usize,
+    confidence: f64,
+    rng: &mut Rng,
+) -> (f64, f64) {
+    let mut ratios: Vec<f64> = (0..resamples)
+        .map(|_| {
+            let mean_a = (0..a.len()).map(|_| a[rng.below(a.len())]).sum::<f64>() / a.len() as f64;
+            let mean_b = (0..b.len()).map(|_| b[rng.below(b.len())]).sum::<f64>() / b.len() as f64;
+            mean_a / mean_b
+        })
+        .collect();
+    ratios.sort_by(|x, y| x.total_cmp(y));
+    let alpha = (1.0 - confidence) / 2.0;
+    (percentile(&ratios, alpha), percentile(&ratios, 1.0 - alpha))
+}
+
+// Complementary error function using the Chebyshev fit `erfcc` from
+// Numerical Recipes, fractional error below 1.2e-7.
+pub fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use symphonia::core::audio::{AudioBufferRef, Signal};
+use symphonia::core::checksum::Md5;
+use symphonia::core::io::Monitor;
+
+use crate::decode::Decoder;
+use crate::flac;
+
+// Verifies FLAC files against the MD5 of the decoded audio stored in
+// STREAMINFO. The hash covers the interleaved samples, little-endian, each
+// truncated to the stream's bit depth rounded up to whole bytes.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Match,
+    Mismatch {
+        stored: [u8; 16],
+        computed: [u8; 16],
+    },
+    // Encoders that don't compute the MD5 store zeroes.
+    Unset {
+        computed: [u8; 16],
+    },
+}
+
+pub fn hex(md5: &[u8; 16]) -> String {
+    md5.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn verify_flac<P: AsRef<Path>>(path: P) -> Result<Verification, Box<dyn std::error::Error>> {
+    let stored = flac::Tag::read_from_path(&path)?
+        .stream_info
+        .ok_or(flac::Error::InvalidInput("missing streaminfo"))?
+        .md5;
+    let computed = decoded_md5(&path)?;
+    Ok(if stored == [0; 16] {
+        Verification::Unset { computed }
+    } else if stored == computed {
+        Verification::Match
+    } else {
+        Verification::Mismatch { stored, computed }
+    })
+}
+
+pub fn decoded_md5<P: AsRef<Path>>(path: P) -> Result<[u8; 16], Box<dyn std::error::Error>> {
+    let mut decoder = Decoder::open(path)?;
+    let bits = match decoder.params.bits_per_sample {
+        Some(bits @ 1..=32) => bits,
+        _ => return Err("unknown bits per sample".into()),
+    };
+    let bytes_per_sample = (bits as usize).div_ceil(8);
+
+    let mut md5 = Md5::default();
+    let mut bytes = Vec::new();
+    while let Some(decoded) = decoder.next_buffer()? {
+        // The FLAC decoder hands out i32 samples scaled up to 32 bits.
+        let buf = match decoded {
+            AudioBufferRef::S32(buf) => buf,
+            _ => return Err("not a FLAC stream".into()),
+        };
+        let channels = buf.spec().channels.count();
+        bytes.clear();
+        for frame in 0..buf.frames() {
+            for channel in 0..channels {
+                let sample = (buf.chan(channel)[frame] >> (32 - bits)).to_le_bytes();
+                bytes.extend_from_slice(&sample[..bytes_per_sample]);
+            }
+        }
+        md5.process_buf_bytes(&bytes);
+    }
+    Ok(md5.md5())
+}
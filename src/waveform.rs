@@ -0,0 +1,115 @@
+use std::path::Path;
+use symphonia::core::errors::{Error, Result};
+
+use crate::decode::Decoder;
+use crate::spectrum::{self, Framer};
+
+// Downsampled views of a file's audio for previews: per bucket minimum and
+// maximum of the mono mix, and a spectrogram in dB.
+
+pub struct Waveform {
+    pub sample_rate: u32,
+    pub frames: u64,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+pub struct Spectrogram {
+    pub sample_rate: u32,
+    pub frames: u64,
+    // One column per analysed frame, each with `fft_size / 2 + 1` bins in dB
+    // relative to a full scale sine.
+    pub columns: Vec<Vec<f64>>,
+}
+
+// Frames in the file, from the container when it knows, otherwise by
+// decoding it once.
+fn frame_count(path: &Path) -> Result<u64> {
+    let mut decoder = Decoder::open_audio_only(path)?;
+    match decoder.params.n_frames {
+        Some(frames) => Ok(frames),
+        None => decoder.for_each_interleaved(|_| ()),
+    }
+}
+
+// The decoder's channel count, an error when it could not find one instead of
+// mixing the samples with the wrong stride.
+fn channels(decoder: &Decoder) -> Result<usize> {
+    match decoder.channels() {
+        0 => Err(Error::Unsupported("unknown channel count")),
+        channels => Ok(channels),
+    }
+}
+
+pub fn waveform<P: AsRef<Path>>(path: P, buckets: usize) -> Result<Waveform> {
+    let path = path.as_ref();
+    let frames = frame_count(path)?;
+    let per_bucket = (frames as usize).div_ceil(buckets.max(1)).max(1);
+
+    let mut decoder = Decoder::open_audio_only(path)?;
+    let channels = channels(&decoder)?;
+    let mut min = Vec::with_capacity(buckets);
+    let mut max = Vec::with_capacity(buckets);
+    let (mut low, mut high, mut filled) = (f32::MAX, f32::MIN, 0);
+    decoder.for_each_interleaved(|samples| {
+        for frame in samples.chunks_exact(channels) {
+            let sample = frame.iter().sum::<f32>() / channels as f32;
+            low = low.min(sample);
+            high = high.max(sample);
+            filled += 1;
+            if filled == per_bucket {
+                min.push(low);
+                max.push(high);
+                (low, high, filled) = (f32::MAX, f32::MIN, 0);
+            }
+        }
+    })?;
+    if filled > 0 {
+        min.push(low);
+        max.push(high);
+    }
+
+    Ok(Waveform {
+        sample_rate: decoder.sample_rate(),
+        frames,
+        min,
+        max,
+    })
+}
+
+// About `columns` spectra of `fft_size` samples, evenly spread over the file.
+// Short files get fewer columns, frames never overlap by more than 75%.
+pub fn spectrogram<P: AsRef<Path>>(
+    path: P,
+    fft_size: usize,
+    columns: usize,
+) -> Result<Spectrogram> {
+    let path = path.as_ref();
+    let frames = frame_count(path)?;
+    let hop = (frames as usize / columns.max(1)).max(fft_size / 4).max(1);
+
+    let mut decoder = Decoder::open_audio_only(path)?;
+    let window = spectrum::hann(fft_size);
+    // A full scale sine peaks at a quarter of the frame length with a Hann
+    // window.
+    let full_scale = spectrum::to_db((fft_size as f64 / 4.0).powi(2));
+    let mut framer = Framer::new(channels(&decoder)?, fft_size, hop);
+    let mut spectra = Vec::with_capacity(columns);
+    decoder.for_each_interleaved(|samples| {
+        framer.push(samples, |frame| {
+            let power = spectrum::power_spectrum(frame, &window);
+            spectra.push(
+                power
+                    .into_iter()
+                    .map(|p| spectrum::to_db(p) - full_scale)
+                    .collect(),
+            );
+        })
+    })?;
+
+    Ok(Spectrogram {
+        sample_rate: decoder.sample_rate(),
+        frames,
+        columns: spectra,
+    })
+}